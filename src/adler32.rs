@@ -0,0 +1,38 @@
+const MOD_ADLER: u32 = 65521;
+// Largest n such that 255n(n+1)/2 + (n+1)(MOD_ADLER-1) fits in a u32
+const NMAX: usize = 5552;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self { a: 1, b: 0 }
+    }
+}
+
+impl Adler32 {
+    pub fn update(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(NMAX) {
+            for &byte in chunk {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= MOD_ADLER;
+            self.b %= MOD_ADLER;
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    let mut hasher = Adler32::default();
+    hasher.update(bytes);
+    hasher.finish()
+}
@@ -1,7 +1,9 @@
-use std::collections::HashMap;
 use std::fmt;
 
 use crate::arz::Record;
+use crate::localization::TagLookup;
+use crate::tags::{select_variant, Form};
+use crate::{Error, Result};
 
 pub const PREFIX_PATH: &str = "records/items/lootaffixes/prefix/";
 pub const SUFFIX_PATH: &str = "records/items/lootaffixes/suffix/";
@@ -14,25 +16,40 @@ pub struct Affix {
 }
 
 impl Affix {
-    pub fn localize(&self, tags: &HashMap<String, String>) -> String {
-        tags.get(&self.tag)
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| self.tag.clone())
+    /// Looks the affix's name up in `tags`, which can be a plain map, a [`Localization`] or a [`LanguageView`]
+    /// with fallbacks, and falls back to the tag itself. Gendered names come back in their masculine singular form.
+    ///
+    /// [`Localization`]: crate::localization::Localization
+    /// [`LanguageView`]: crate::localization::LanguageView
+    pub fn localize<L: TagLookup + ?Sized>(&self, tags: &L) -> String {
+        self.localize_as(tags, Form::default())
+    }
+
+    /// Like [`Affix::localize`], but picks the variant agreeing with `form`, such as the gender of the item name
+    /// found with [`Form::of`].
+    pub fn localize_as<L: TagLookup + ?Sized>(&self, tags: &L, form: Form) -> String {
+        select_variant(tags.lookup(&self.tag).unwrap_or(&self.tag), form)
     }
 }
 
-impl From<Record> for Affix {
-    fn from(record: Record) -> Self {
-        Self {
+impl TryFrom<Record> for Affix {
+    type Error = Error;
+
+    fn try_from(record: Record) -> Result<Self> {
+        let tag = match record.data.get("lootRandomizerName") {
+            Some(val) => val.as_string().ok_or_else(|| Error::WrongFieldType {
+                record: record.id.clone(),
+                field: "lootRandomizerName".to_string(),
+                expected: "string",
+            })?,
+            None => record.id.clone(),
+        };
+        Ok(Self {
             id: record.id.clone(),
-            tag: record
-                .data
-                .get("lootRandomizerName")
-                .map(|val| val.as_string().unwrap())
-                .unwrap_or_else(|| record.id.clone()),
+            tag,
             description: record.data.get("FileDescription").map(|desc| desc.to_string()),
             record,
-        }
+        })
     }
 }
 
@@ -42,7 +59,7 @@ impl fmt::Display for Affix {
             f,
             "Affix {{ id: {}, desc: {}, tag: {} }}",
             self.id,
-            self.description.as_ref().map(|d| d.as_str()).unwrap_or("undefined"),
+            self.description.as_deref().unwrap_or("undefined"),
             self.tag
         )
     }
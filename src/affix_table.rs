@@ -1,6 +1,7 @@
 use std::ops::Range;
 
 use crate::arz::Record;
+use crate::{Error, Result};
 
 const NAME: &str = "randomizerName";
 const WEIGHT: &str = "randomizerWeight";
@@ -15,47 +16,53 @@ pub struct AffixTable {
     pub ranges: Vec<Range<u32>>,
 }
 
-impl From<&Record> for AffixTable {
-    fn from(record: &Record) -> Self {
+// Field names are 1-indexed, e.g. randomizerName1
+fn slot(key: &str, prefix: &str) -> Option<usize> {
+    key.strip_prefix(prefix)?.parse::<usize>().ok()?.checked_sub(1)
+}
+
+fn grow<T: Clone>(values: &mut Vec<T>, i: usize, fill: T) {
+    if i >= values.len() {
+        values.resize(i + 1, fill);
+    }
+}
+
+impl TryFrom<&Record> for AffixTable {
+    type Error = Error;
+
+    fn try_from(record: &Record) -> Result<Self> {
         let estimated_len = record.data.len() / 4;
         let mut affixes = vec!["".to_string(); estimated_len];
         let mut weights = vec![0f32; estimated_len];
         let mut ranges = vec![0..1; estimated_len];
         for (key, value) in record.data.iter() {
-            if key.starts_with(NAME) {
-                let i = key[NAME.len()..].parse::<usize>().unwrap() - 1;
-                if i > affixes.len() {
-                    affixes.extend_from_slice(&vec!["".to_string(); i - affixes.len()])
-                };
-                affixes[i] = value.as_string().unwrap();
-            } else if key.starts_with(WEIGHT) {
-                let i = key[WEIGHT.len()..].parse::<usize>().unwrap() - 1;
-                if i > weights.len() {
-                    weights.extend_from_slice(&vec![0f32; i - weights.len()])
-                };
-                weights[i] = value.as_float().unwrap();
-            } else if key.starts_with(MIN) {
-                let i = key[MIN.len()..].parse::<usize>().unwrap() - 1;
-                if i > ranges.len() {
-                    ranges.extend_from_slice(&vec![0..1; i - ranges.len()])
-                };
-                let min = value.as_int().unwrap();
-                ranges[i] = min..ranges[i].end.min(min + 1);
-            } else if key.starts_with(MAX) {
-                let i = key[MAX.len()..].parse::<usize>().unwrap() - 1;
-                if i > ranges.len() {
-                    ranges.extend_from_slice(&vec![0..1; i - ranges.len()])
-                };
-                let max = value.as_int().unwrap();
+            let wrong_type = |expected| Error::WrongFieldType {
+                record: record.id.clone(),
+                field: key.clone(),
+                expected,
+            };
+            if let Some(i) = slot(key, NAME) {
+                grow(&mut affixes, i, "".to_string());
+                affixes[i] = value.as_string().ok_or_else(|| wrong_type("string"))?;
+            } else if let Some(i) = slot(key, WEIGHT) {
+                grow(&mut weights, i, 0f32);
+                weights[i] = value.as_float().ok_or_else(|| wrong_type("float"))?;
+            } else if let Some(i) = slot(key, MIN) {
+                grow(&mut ranges, i, 0..1);
+                let min = value.as_int().ok_or_else(|| wrong_type("int"))?;
+                ranges[i] = min..ranges[i].end.min(min.saturating_add(1));
+            } else if let Some(i) = slot(key, MAX) {
+                grow(&mut ranges, i, 0..1);
+                let max = value.as_int().ok_or_else(|| wrong_type("int"))?;
                 ranges[i] = ranges[i].start..max;
             }
         }
 
-        Self {
+        Ok(Self {
             id: record.id.clone(),
             affixes,
             weights,
             ranges,
-        }
+        })
     }
 }
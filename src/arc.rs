@@ -1,15 +1,41 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Cursor, Result, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::time::SystemTime;
 
 use crate::buf_read_ext::BufReadExt;
+use crate::error::{check_bounds, check_length, check_plausible};
+use crate::filetime::from_filetime;
+use crate::read_at::{ReadAt, SharedFile};
+#[cfg(feature = "mmap")]
+use crate::MappedFile;
+use crate::{Error, Result};
+
+mod extract;
+#[cfg(feature = "rayon")]
+mod par;
+mod reader;
+mod resource_fs;
+mod writer;
+
+pub use extract::{ExtractOptions, ExtractReport};
+pub use reader::EntryReader;
+pub use resource_fs::ResourceFs;
+pub use writer::ArchiveWriter;
 
 const ARC_MAGIC: u32 = 4411969;
 const SUPPORTED_VERSIONS: [u32; 1] = [3];
+const HEADER_LEN: u64 = 28;
+const BLOCK_LEN: u64 = 12;
+const METADATA_LEN: u64 = 44;
 
 #[allow(dead_code)]
 pub struct Archive<T> {
     file: T,
+    index: HashMap<String, Metadata>,
+    file_len: u64,
     head: u32,
     version: u32,
     record_count: u32,
@@ -46,7 +72,57 @@ pub struct Record {
     pub data: Vec<u8>,
 }
 
-impl<R: BufRead + Seek> Archive<R> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageType {
+    Stored,
+    Lz4,
+    Unknown(u32),
+}
+
+impl From<u32> for StorageType {
+    fn from(version: u32) -> Self {
+        match version {
+            1 => Self::Stored,
+            3 => Self::Lz4,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl From<StorageType> for u32 {
+    fn from(storage: StorageType) -> Self {
+        match storage {
+            StorageType::Stored => 1,
+            StorageType::Lz4 => 3,
+            StorageType::Unknown(other) => other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub compressed_len: u32,
+    pub uncompressed_len: u32,
+    pub block_count: u32,
+    pub storage: StorageType,
+    pub last_modified_at: SystemTime,
+}
+
+impl Entry {
+    fn new(name: &str, metadata: &Metadata) -> Self {
+        Self {
+            name: name.to_string(),
+            compressed_len: metadata.compressed_len,
+            uncompressed_len: metadata.uncompressed_len,
+            block_count: metadata.block_count,
+            storage: metadata.version.into(),
+            last_modified_at: from_filetime(metadata.last_modified_at),
+        }
+    }
+}
+
+impl<T> Archive<T> {
     fn block_list_offset(&self) -> u64 {
         self.block_list_offset as u64
     }
@@ -59,65 +135,98 @@ impl<R: BufRead + Seek> Archive<R> {
         self.record_list_offset() + self.record_list_len as u64
     }
 
-    pub fn get(&mut self, id: &str) -> Result<Record> {
-        let result = self.iter_record_names()?.enumerate().find(|(_, name)| match name {
-            Ok(name) => id == name,
-            Err(_) => false,
-        });
-        match result {
-            Some((i, id)) => {
-                let id = id?;
-                let metadata_result = self.iter_metadata()?.find(|metadata| i as u32 == metadata.as_ref().unwrap().index);
-                metadata_result
-                    .map(|metadata| self.get_inner(metadata?, &id))
-                    .ok_or_else(|| std::io::Error::other(format!("Failed to get {id}")))?
-            }
-            None => Err(std::io::Error::other(format!("Failed to get {id}"))),
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn entry(&self, id: &str) -> Option<Entry> {
+        self.index.get(id).map(|metadata| Entry::new(id, metadata))
+    }
+
+    /// Lists the archive's entries in the order their data is stored, without reading any of it.
+    pub fn entries(&self) -> Vec<Entry> {
+        self.index_in_file_order()
+            .into_iter()
+            .map(|(name, metadata)| Entry::new(name, metadata))
+            .collect()
+    }
+
+    fn index_in_file_order(&self) -> Vec<(&String, &Metadata)> {
+        let mut entries = self.index.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(name, metadata)| (metadata.offset, metadata.index, *name));
+        entries
+    }
+
+    fn with_file<U>(self, file: U) -> Archive<U> {
+        Archive {
+            file,
+            index: self.index,
+            file_len: self.file_len,
+            head: self.head,
+            version: self.version,
+            record_count: self.record_count,
+            block_count: self.block_count,
+            block_list_offset: self.block_list_offset,
+            block_list_len: self.block_list_len,
+            record_list_len: self.record_list_len,
         }
     }
+}
+
+impl<R: BufRead + Seek> Archive<R> {
+    pub fn get(&mut self, id: &str) -> Result<Record> {
+        let metadata = *self
+            .index
+            .get(id)
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        self.get_inner(metadata, id)
+    }
 
     fn get_inner(&mut self, metadata: Metadata, id: &str) -> Result<Record> {
-        let block_count = metadata.block_count as usize;
-        let index = metadata.index as usize;
-        let blocks = self.blocks(index, block_count)?.collect::<Result<Vec<_>>>()?;
-        let mut data = vec![0u8; metadata.uncompressed_len as usize];
-        let mut offset = 0;
-        for block in blocks.into_iter() {
-            let _ = self.file.seek(SeekFrom::Start(block.offset as u64));
-            if block.uncompressed_len == block.compressed_len {
-                let _ = self
-                    .file
-                    .read_exact(&mut data[offset..offset + block.uncompressed_len as usize]);
-            } else {
-                let mut compressed = vec![0u8; block.compressed_len as usize];
-                let _ = self.file.read_exact(&mut compressed);
-                lz4::block::decompress_to_buffer(
-                    &compressed[..],
-                    Some(block.uncompressed_len as i32),
-                    &mut data[offset..offset + block.uncompressed_len as usize],
-                )?;
-            }
-            offset += block.uncompressed_len as usize;
-        }
+        let blocks = self.read_blocks(&metadata)?;
         Ok(Record {
             id: id.to_string(),
-            data,
+            data: decode_blocks(id, &metadata, blocks)?,
         })
     }
 
+    fn read_blocks(&mut self, metadata: &Metadata) -> Result<Vec<(Block, Vec<u8>)>> {
+        let block_count = metadata.block_count as usize;
+        let index = metadata.index as usize;
+        let blocks = self.blocks(index, block_count)?.collect::<Result<Vec<_>>>()?;
+        blocks
+            .into_iter()
+            .map(|block| {
+                self.file.seek(SeekFrom::Start(block.offset as u64))?;
+                let mut raw = vec![0u8; block.compressed_len as usize];
+                self.file.read_exact(&mut raw)?;
+                Ok((block, raw))
+            })
+            .collect()
+    }
+
     pub fn iter_records(&mut self) -> Result<impl Iterator<Item = Result<Record>> + '_> {
         let metadata = self.iter_metadata()?.collect::<Result<Vec<_>>>()?;
         let record_names = self.iter_record_names()?.collect::<Result<Vec<_>>>()?;
         assert_eq!(metadata.len(), record_names.len());
         Ok(metadata
             .into_iter()
-            .zip(record_names.into_iter())
+            .zip(record_names)
             .map(move |(metadata, id)| self.get_inner(metadata, &id)))
     }
 
     pub fn iter_metadata(&mut self) -> Result<impl Iterator<Item = Result<Metadata>> + '_> {
-        let _ = self.file.seek(SeekFrom::Start(self.metadata_offset()))?;
+        self.file.seek(SeekFrom::Start(self.metadata_offset()))?;
         Ok((0..self.record_count).map(|_| {
+            let metadata_offset = self.file.stream_position()?;
             let version = self.file.read_u32()?;
             let offset = self.file.read_u32()?;
             let compressed_len = self.file.read_u32()?;
@@ -128,6 +237,16 @@ impl<R: BufRead + Seek> Archive<R> {
             let index = self.file.read_u32()?;
             let record_name_len = self.file.read_u32()?;
             let record_name_offset = self.file.read_u32()?;
+            check_bounds(None, offset as u64, compressed_len as u64, self.file_len)?;
+            check_plausible(None, metadata_offset, compressed_len, uncompressed_len)?;
+            if index as u64 + block_count as u64 > self.block_count as u64 {
+                return Err(Error::OutOfBounds {
+                    record: None,
+                    offset: self.block_list_offset() + index as u64 * BLOCK_LEN,
+                    len: block_count as u64 * BLOCK_LEN,
+                    file_len: self.file_len,
+                });
+            }
             Ok(Metadata {
                 version,
                 offset,
@@ -144,48 +263,71 @@ impl<R: BufRead + Seek> Archive<R> {
 
     fn blocks(&mut self, index: usize, len: usize) -> Result<impl Iterator<Item = Result<Block>> + '_> {
         // Each block is 12 bytes, so we can seek directly to the index
-        let _ = self
-            .file
-            .seek(SeekFrom::Start(self.block_list_offset() + index as u64 * 12))?;
+        self.file
+            .seek(SeekFrom::Start(self.block_list_offset() + index as u64 * BLOCK_LEN))?;
         Ok((0..len).map(|_| {
-            let offset = self.file.read_u32()?;
-            let compressed_len = self.file.read_u32()?;
-            let uncompressed_len = self.file.read_u32()?;
-            Ok(Block {
-                offset,
-                compressed_len,
-                uncompressed_len,
-            })
+            let position = self.file.stream_position()?;
+            read_block(&mut self.file, position, self.file_len)
         }))
     }
 
     pub fn iter_record_names(&mut self) -> Result<impl Iterator<Item = Result<String>> + '_> {
-        let _ = self.file.seek(SeekFrom::Start(self.record_list_offset()))?;
-        Ok((0..self.record_count).map(|_| {
+        let end = self.metadata_offset();
+        self.file.seek(SeekFrom::Start(self.record_list_offset()))?;
+        Ok((0..self.record_count).map(move |_| {
+            let offset = self.file.stream_position()?;
             let mut buf = vec![];
-            let _ = self.file.read_until(0, &mut buf);
-            std::str::from_utf8(&buf[0..buf.len() - 1])
+            (&mut self.file).take(end - offset).read_until(0, &mut buf)?;
+            if buf.pop() != Some(0) {
+                return Err(Error::OutOfBounds {
+                    record: None,
+                    offset,
+                    len: buf.len() as u64 + 1,
+                    file_len: end,
+                });
+            }
+            std::str::from_utf8(&buf[..])
                 .map(|s| s.to_owned())
-                .map_err(|_| std::io::Error::other(format!("Found non-utf8 bytes in record name")))
+                .map_err(|_| Error::NonUtf8Name { record: None, offset })
         }))
     }
 
-    fn from(mut buf: R) -> Result<Self> {
+    fn build_index(mut self) -> Result<Self> {
+        let metadata = self.iter_metadata()?.collect::<Result<Vec<_>>>()?;
+        let record_names = self.iter_record_names()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(metadata.len(), record_names.len());
+        self.index = record_names.into_iter().zip(metadata).collect();
+        Ok(self)
+    }
+
+    /// Reads from any seekable source whose data starts at offset 0.
+    pub fn from_reader(mut buf: R) -> Result<Self> {
+        let file_len = buf.seek(SeekFrom::End(0))?;
+        buf.seek(SeekFrom::Start(0))?;
         let head = buf.read_u32()?;
         if head != ARC_MAGIC {
-            return Err(std::io::Error::other(format!("Unexpected magic number {head}")));
+            return Err(Error::BadMagic { offset: 0, found: head });
         }
         let version = buf.read_u32()?;
         if !SUPPORTED_VERSIONS.contains(&version) {
-            return Err(std::io::Error::other(format!("Unsupported version {version}")));
+            return Err(Error::UnsupportedVersion { offset: 4, version });
         }
         let record_count = buf.read_u32()?;
         let block_count = buf.read_u32()?;
         let block_list_len = buf.read_u32()?;
         let record_list_len = buf.read_u32()?;
         let block_list_offset = buf.read_u32()?;
-        Ok(Self {
+        let block_list_offset_u64 = block_list_offset as u64;
+        check_bounds(None, block_list_offset_u64, block_list_len as u64, file_len)?;
+        check_bounds(None, block_list_offset_u64, block_count as u64 * BLOCK_LEN, file_len)?;
+        let record_list_offset = block_list_offset_u64 + block_list_len as u64;
+        check_bounds(None, record_list_offset, record_list_len as u64, file_len)?;
+        let metadata_offset = record_list_offset + record_list_len as u64;
+        check_bounds(None, metadata_offset, record_count as u64 * METADATA_LEN, file_len)?;
+        Self {
             file: buf,
+            index: Default::default(),
+            file_len,
             head,
             version,
             record_count,
@@ -193,20 +335,163 @@ impl<R: BufRead + Seek> Archive<R> {
             block_list_len,
             record_list_len,
             block_list_offset,
-        })
+        }
+        .build_index()
+    }
+}
+
+fn read_block<B: BufRead>(buf: &mut B, position: u64, file_len: u64) -> Result<Block> {
+    let offset = buf.read_u32()?;
+    let compressed_len = buf.read_u32()?;
+    let uncompressed_len = buf.read_u32()?;
+    check_bounds(None, offset as u64, compressed_len as u64, file_len)?;
+    check_plausible(None, position, compressed_len, uncompressed_len)?;
+    Ok(Block {
+        offset,
+        compressed_len,
+        uncompressed_len,
+    })
+}
+
+fn decode_block(id: &str, block: &Block, raw: &[u8], out: &mut [u8]) -> Result<()> {
+    if block.uncompressed_len == block.compressed_len {
+        out.copy_from_slice(raw);
+        return Ok(());
+    }
+    let len = lz4::block::decompress_to_buffer(raw, Some(block.uncompressed_len as i32), out).map_err(|source| {
+        Error::Lz4 {
+            record: id.to_string(),
+            offset: block.offset as u64,
+            source,
+        }
+    })?;
+    check_length(Some(id), block.offset as u64, block.uncompressed_len as u64, len as u64)
+}
+
+fn decode_blocks<B>(id: &str, metadata: &Metadata, mut blocks: Vec<(Block, B)>) -> Result<Vec<u8>>
+where
+    B: AsRef<[u8]> + Into<Vec<u8>>,
+{
+    // A single stored block is already the file's contents
+    if let [(block, _)] = &blocks[..] {
+        if block.compressed_len == block.uncompressed_len && block.uncompressed_len == metadata.uncompressed_len {
+            return Ok(blocks.remove(0).1.into());
+        }
+    }
+    let mut data = vec![0u8; metadata.uncompressed_len as usize];
+    let mut offset = 0;
+    for (block, raw) in blocks.iter() {
+        let len = block.uncompressed_len as usize;
+        if offset + len > data.len() {
+            return Err(Error::ImplausibleLength {
+                record: Some(id.to_string()),
+                offset: block.offset as u64,
+                len: block.uncompressed_len as u64,
+            });
+        }
+        decode_block(id, block, raw.as_ref(), &mut data[offset..offset + len])?;
+        offset += len;
     }
+    check_length(Some(id), metadata.offset as u64, data.len() as u64, offset as u64)?;
+    Ok(data)
 }
 
-impl Archive<Cursor<&'static [u8]>> {
-    pub fn parse(bytes: &'static [u8]) -> Result<Self> {
+impl<B: AsRef<[u8]>> Archive<Cursor<B>> {
+    /// Reads from an in-memory buffer, borrowed (`&[u8]`) or owned (`Vec<u8>`, `Arc<[u8]>`, ...).
+    pub fn parse(bytes: B) -> Result<Self> {
         let read = Cursor::new(bytes);
-        Self::from(read)
+        Self::from_reader(read)
     }
 }
 
 impl Archive<BufReader<File>> {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = BufReader::new(File::open(path)?);
-        Self::from(file)
+        Self::from_reader(file)
+    }
+}
+
+impl<S: ReadAt> Archive<S> {
+    fn block_list_at(&self, metadata: &Metadata) -> Result<Vec<Block>> {
+        let start = self.block_list_offset() + metadata.index as u64 * BLOCK_LEN;
+        let list = self
+            .file
+            .read_at(start, metadata.block_count as usize * BLOCK_LEN as usize)?;
+        let mut buf = &list[..];
+        (0..metadata.block_count as u64)
+            .map(|i| read_block(&mut buf, start + i * BLOCK_LEN, self.file_len))
+            .collect()
+    }
+
+    fn read_blocks_at(&self, metadata: &Metadata) -> Result<Vec<(Block, Cow<'_, [u8]>)>> {
+        self.block_list_at(metadata)?
+            .into_iter()
+            .map(|block| {
+                let raw = self.file.read_at(block.offset as u64, block.compressed_len as usize)?;
+                Ok((block, raw))
+            })
+            .collect()
+    }
+
+    fn get_at(&self, id: &str) -> Result<Record> {
+        let metadata = self
+            .index
+            .get(id)
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        let blocks = self.read_blocks_at(metadata)?;
+        Ok(Record {
+            id: id.to_string(),
+            data: decode_blocks(id, metadata, blocks)?,
+        })
+    }
+
+    fn iter_records_at(&self) -> impl Iterator<Item = Result<Record>> + '_ {
+        self.index_in_file_order().into_iter().map(|(id, metadata)| {
+            let blocks = self.read_blocks_at(metadata)?;
+            Ok(Record {
+                id: id.clone(),
+                data: decode_blocks(id, metadata, blocks)?,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Archive<MappedFile> {
+    /// Opens an archive through a memory map, so entries can be read through a shared reference.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the archive is open, see [`MappedFile::open`].
+    pub unsafe fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        // SAFETY: upheld by the caller
+        let map = unsafe { MappedFile::open(path)? };
+        let archive = Archive::from_reader(Cursor::new(map.as_ref()))?.with_file(());
+        Ok(archive.with_file(map))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Record> {
+        self.get_at(id)
+    }
+
+    pub fn iter_records(&self) -> impl Iterator<Item = Result<Record>> + '_ {
+        self.iter_records_at()
+    }
+}
+
+impl Archive<SharedFile> {
+    /// Opens an archive that can be shared between threads, each reading entries through its own positional reads.
+    pub fn open_shared<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = SharedFile::open(path)?;
+        let archive = Archive::from_reader(BufReader::new(file.try_clone()?))?;
+        Ok(archive.with_file(file))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Record> {
+        self.get_at(id)
+    }
+
+    pub fn iter_records(&self) -> impl Iterator<Item = Result<Record>> + '_ {
+        self.iter_records_at()
     }
 }
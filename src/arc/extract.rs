@@ -0,0 +1,184 @@
+use std::fs::{self, File};
+use std::io::{BufRead, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use super::{decode_blocks, Archive, Block, Metadata};
+use crate::filetime::from_filetime;
+use crate::path::relative_path;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Number of threads decompressing and writing entries. Reads from the archive always happen on the calling
+    /// thread.
+    pub threads: usize,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self { threads: 1 }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    pub extracted: Vec<PathBuf>,
+    pub failed: Vec<(String, Error)>,
+}
+
+impl ExtractReport {
+    fn record(&mut self, name: String, result: Result<PathBuf>) {
+        match result {
+            Ok(path) => self.extracted.push(path),
+            Err(err) => self.failed.push((name, err)),
+        }
+    }
+}
+
+struct Job {
+    name: String,
+    metadata: Metadata,
+    blocks: Vec<(Block, Vec<u8>)>,
+}
+
+impl<R: BufRead + Seek> Archive<R> {
+    pub fn extract_all<P: AsRef<Path>>(&mut self, dest: P, options: &ExtractOptions) -> Result<ExtractReport> {
+        self.extract_filtered(dest, options, |_| true)
+    }
+
+    /// Extracts the entries whose names pass `filter` below `dest`, keeping their last modified times.
+    ///
+    /// Failures are collected per entry in the report, and only a failure to create `dest` itself is returned as
+    /// an error.
+    pub fn extract_filtered<P, F>(&mut self, dest: P, options: &ExtractOptions, filter: F) -> Result<ExtractReport>
+    where
+        P: AsRef<Path>,
+        F: Fn(&str) -> bool,
+    {
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+        let mut entries = self
+            .index
+            .iter()
+            .filter(|(name, _)| filter(name))
+            .map(|(name, metadata)| (name.clone(), *metadata))
+            .collect::<Vec<_>>();
+        // Visit entries in file order so the reads stay sequential
+        entries.sort_by_key(|(_, metadata)| metadata.offset);
+
+        let mut report = ExtractReport::default();
+        if options.threads <= 1 {
+            for (name, metadata) in entries {
+                let result = self
+                    .read_blocks(&metadata)
+                    .and_then(|blocks| write_entry(dest, &name, &metadata, blocks));
+                report.record(name, result);
+            }
+        } else {
+            let shared_report = Mutex::new(report);
+            let (sender, receiver) = mpsc::sync_channel::<Job>(options.threads * 2);
+            let receiver = Mutex::new(receiver);
+            thread::scope(|scope| {
+                for _ in 0..options.threads {
+                    scope.spawn(|| loop {
+                        let job = match receiver.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+                        let result = write_entry(dest, &job.name, &job.metadata, job.blocks);
+                        shared_report.lock().unwrap().record(job.name, result);
+                    });
+                }
+                for (name, metadata) in entries {
+                    match self.read_blocks(&metadata) {
+                        Ok(blocks) => {
+                            let job = Job { name, metadata, blocks };
+                            if sender.send(job).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => shared_report.lock().unwrap().failed.push((name, err)),
+                    }
+                }
+                drop(sender);
+            });
+            report = shared_report.into_inner().unwrap();
+        }
+        report.extracted.sort();
+        report.failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(report)
+    }
+}
+
+fn write_entry(dest: &Path, name: &str, metadata: &Metadata, blocks: Vec<(Block, Vec<u8>)>) -> Result<PathBuf> {
+    let path = relative_path(name).ok_or_else(|| Error::UnsafePath { name: name.to_string() })?;
+    let path = dest.join(path);
+    let data = decode_blocks(name, metadata, blocks)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(&path)?;
+    file.write_all(&data)?;
+    file.set_modified(from_filetime(metadata.last_modified_at))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::arc::ArchiveWriter;
+
+    fn files_below(dir: &Path) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(files_below(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn extract_refuses_unsafe_names() {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let mut writer = ArchiveWriter::new();
+        writer.add("../evil.txt", b"evil".to_vec(), modified);
+        writer.add("C:/x", b"drive".to_vec(), modified);
+        writer.add("a\\b\\c.txt", b"nested".to_vec(), modified);
+        writer.add("safe.txt", vec![7; 300_000], modified);
+        let path = std::env::temp_dir().join(format!("lib_gddb_extract_{}.arc", std::process::id()));
+        writer.write_file(&path).unwrap();
+
+        for threads in [1, 4] {
+            let root = std::env::temp_dir().join(format!("lib_gddb_extract_{}_{threads}", std::process::id()));
+            let dest = root.join("out");
+            let mut archive = Archive::open(&path).unwrap();
+            let report = archive.extract_all(&dest, &ExtractOptions { threads }).unwrap();
+
+            let failed = report.failed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+            assert_eq!(failed, ["../evil.txt", "C:/x"], "{threads} threads");
+            assert!(report
+                .failed
+                .iter()
+                .all(|(_, err)| matches!(err, Error::UnsafePath { .. })));
+            let expected = [dest.join("a").join("b").join("c.txt"), dest.join("safe.txt")];
+            assert_eq!(report.extracted, expected);
+            assert_eq!(files_below(&root), expected);
+            assert_eq!(fs::read(&expected[0]).unwrap(), b"nested");
+            assert_eq!(fs::read(&expected[1]).unwrap(), vec![7; 300_000]);
+            for file in &expected {
+                assert_eq!(fs::metadata(file).unwrap().modified().unwrap(), modified);
+            }
+            fs::remove_dir_all(root).unwrap();
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{BufRead, Seek};
+use std::iter;
+
+use rayon::prelude::*;
+
+use super::{decode_blocks, Archive, Record};
+#[cfg(feature = "mmap")]
+use crate::read_at::MappedFile;
+use crate::read_at::{ReadAt, SharedFile};
+use crate::Result;
+
+// Compressed bytes read ahead of each parallel decode, which bounds the memory held at once
+const PAR_CHUNK_LEN: u64 = 32 * 1024 * 1024;
+
+impl<R: BufRead + Seek> Archive<R> {
+    /// Decompresses every entry on the rayon thread pool, yielding them in file order.
+    ///
+    /// The compressed blocks are read on the calling thread in chunks of about 32 MiB, and each chunk is
+    /// decompressed in parallel before the next one is read. Errors are reported per entry.
+    pub fn par_records(&mut self) -> impl Iterator<Item = Result<Record>> + '_ {
+        let mut entries = self
+            .index_in_file_order()
+            .into_iter()
+            .map(|(name, metadata)| (name.clone(), *metadata))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+        iter::from_fn(move || {
+            entries.peek()?;
+            let mut chunk = vec![];
+            let mut len = 0u64;
+            while len < PAR_CHUNK_LEN {
+                let Some((name, metadata)) = entries.next() else {
+                    break;
+                };
+                len += metadata.compressed_len as u64;
+                let blocks = self.read_blocks(&metadata);
+                chunk.push((name, metadata, blocks));
+            }
+            let records = chunk
+                .into_par_iter()
+                .map(|(id, metadata, blocks)| {
+                    let data = decode_blocks(&id, &metadata, blocks?)?;
+                    Ok(Record { id, data })
+                })
+                .collect::<Vec<_>>();
+            Some(records)
+        })
+        .flatten()
+    }
+}
+
+impl<S: ReadAt + Sync> Archive<S> {
+    fn par_records_at(&self) -> impl ParallelIterator<Item = Result<Record>> + '_ {
+        self.index.par_iter().map(|(id, metadata)| {
+            let blocks = self.read_blocks_at(metadata)?;
+            Ok(Record {
+                id: id.clone(),
+                data: decode_blocks(id, metadata, blocks)?,
+            })
+        })
+    }
+}
+
+impl Archive<SharedFile> {
+    /// Decompresses every entry on the rayon thread pool. Errors are reported per entry.
+    pub fn par_records(&self) -> impl ParallelIterator<Item = Result<Record>> + '_ {
+        self.par_records_at()
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Archive<MappedFile> {
+    /// Decompresses every entry on the rayon thread pool. Errors are reported per entry.
+    pub fn par_records(&self) -> impl ParallelIterator<Item = Result<Record>> + '_ {
+        self.par_records_at()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::arc::ArchiveWriter;
+
+    #[test]
+    fn par_records_in_file_order() {
+        let mut writer = ArchiveWriter::new();
+        for i in 0..20u8 {
+            writer.add(
+                format!("entry_{i:02}"),
+                vec![i; 10_000 * i as usize],
+                SystemTime::UNIX_EPOCH,
+            );
+        }
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let mut archive = Archive::from_reader(Cursor::new(bytes)).unwrap();
+        let records = archive.par_records().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(records.len(), 20);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.id, format!("entry_{i:02}"));
+            assert_eq!(record.data, vec![i as u8; 10_000 * i]);
+        }
+    }
+}
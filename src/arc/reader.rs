@@ -0,0 +1,304 @@
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+use super::{decode_block, Archive, Block, Metadata};
+use crate::error::check_length;
+#[cfg(feature = "mmap")]
+use crate::read_at::MappedFile;
+use crate::read_at::{ReadAt, SharedFile};
+use crate::{Error, Result};
+
+/// Streams one archive entry, decompressing a single block at a time.
+pub struct EntryReader<'a, R> {
+    source: Source<'a, R>,
+    id: String,
+    blocks: Vec<Block>,
+    // Uncompressed offset at which each block starts
+    starts: Vec<u64>,
+    len: u64,
+    position: u64,
+    current: Option<usize>,
+    buf: Vec<u8>,
+}
+
+// Sequential readers need the archive to themselves, while positional reads only need its file
+enum Source<'a, R> {
+    Archive(&'a mut Archive<R>),
+    File(&'a R),
+}
+
+impl<R: BufRead + Seek> Archive<R> {
+    pub fn open_entry(&mut self, id: &str) -> Result<EntryReader<'_, R>> {
+        let metadata = *self
+            .index
+            .get(id)
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        let blocks = self
+            .blocks(metadata.index as usize, metadata.block_count as usize)?
+            .collect::<Result<Vec<_>>>()?;
+        EntryReader::new(Source::Archive(self), id, &metadata, blocks)
+    }
+}
+
+impl<S: ReadAt> Archive<S> {
+    fn open_entry_at(&self, id: &str) -> Result<EntryReader<'_, S>> {
+        let metadata = self
+            .index
+            .get(id)
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        let blocks = self.block_list_at(metadata)?;
+        EntryReader::new(Source::File(&self.file), id, metadata, blocks)
+    }
+}
+
+impl Archive<SharedFile> {
+    pub fn open_entry(&self, id: &str) -> Result<EntryReader<'_, SharedFile>> {
+        self.open_entry_at(id)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Archive<MappedFile> {
+    pub fn open_entry(&self, id: &str) -> Result<EntryReader<'_, MappedFile>> {
+        self.open_entry_at(id)
+    }
+}
+
+impl<'a, R> EntryReader<'a, R> {
+    fn new(source: Source<'a, R>, id: &str, metadata: &Metadata, blocks: Vec<Block>) -> Result<Self> {
+        let mut starts = Vec::with_capacity(blocks.len());
+        let mut len = 0u64;
+        for block in blocks.iter() {
+            starts.push(len);
+            len += block.uncompressed_len as u64;
+        }
+        check_length(Some(id), metadata.offset as u64, metadata.uncompressed_len as u64, len)?;
+        Ok(Self {
+            source,
+            id: id.to_string(),
+            blocks,
+            starts,
+            len,
+            position: 0,
+            current: None,
+            buf: vec![],
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn read_with<F>(&mut self, out: &mut [u8], load: F) -> io::Result<usize>
+    where
+        F: FnOnce(&mut Self, usize) -> Result<()>,
+    {
+        if self.position >= self.len || out.is_empty() {
+            return Ok(0);
+        }
+        // Empty blocks never contain the position, so take the last block starting at or before it
+        let i = self.starts.partition_point(|&start| start <= self.position) - 1;
+        if self.current != Some(i) {
+            self.current = None;
+            load(self, i)?;
+            self.current = Some(i);
+        }
+        let start = (self.position - self.starts[i]) as usize;
+        let n = (self.buf.len() - start).min(out.len());
+        out[..n].copy_from_slice(&self.buf[start..start + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn seek_to(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+impl<R: BufRead + Seek> EntryReader<'_, R> {
+    fn load(&mut self, i: usize) -> Result<()> {
+        let block = self.blocks[i];
+        let Source::Archive(archive) = &mut self.source else {
+            unreachable!("sequential archives are always read through the archive")
+        };
+        let file = &mut archive.file;
+        file.seek(SeekFrom::Start(block.offset as u64))?;
+        self.buf.resize(block.uncompressed_len as usize, 0);
+        if block.uncompressed_len == block.compressed_len {
+            file.read_exact(&mut self.buf)?;
+        } else {
+            let mut raw = vec![0u8; block.compressed_len as usize];
+            file.read_exact(&mut raw)?;
+            decode_block(&self.id, &block, &raw, &mut self.buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: ReadAt> EntryReader<'_, S> {
+    fn load_at(&mut self, i: usize) -> Result<()> {
+        let block = self.blocks[i];
+        let Source::File(file) = self.source else {
+            unreachable!("positional archives are always read through their file")
+        };
+        let raw = file.read_at(block.offset as u64, block.compressed_len as usize)?;
+        self.buf.resize(block.uncompressed_len as usize, 0);
+        decode_block(&self.id, &block, &raw, &mut self.buf)
+    }
+}
+
+impl<R: BufRead + Seek> Read for EntryReader<'_, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.read_with(out, Self::load)
+    }
+}
+
+impl<R: BufRead + Seek> Seek for EntryReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.seek_to(pos)
+    }
+}
+
+impl Read for EntryReader<'_, SharedFile> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.read_with(out, Self::load_at)
+    }
+}
+
+impl Seek for EntryReader<'_, SharedFile> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.seek_to(pos)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Read for EntryReader<'_, MappedFile> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.read_with(out, Self::load_at)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Seek for EntryReader<'_, MappedFile> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.seek_to(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::arc::{ArchiveWriter, ResourceFs};
+
+    #[test]
+    fn stream_entries_through_positional_reads() {
+        let data = (0..600_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut writer = ArchiveWriter::new();
+        writer.add("Items/Sword.tex", data.clone(), SystemTime::UNIX_EPOCH);
+        let path = std::env::temp_dir().join(format!("lib_gddb_reader_{}.arc", std::process::id()));
+        writer.write_file(&path).unwrap();
+
+        let archive = Archive::open_shared(&path).unwrap();
+        let mut streamed = vec![];
+        archive
+            .open_entry("Items/Sword.tex")
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, data);
+
+        let mut resources = ResourceFs::new();
+        resources.mount("items", Archive::open_shared(&path).unwrap());
+        let mut streamed = vec![];
+        resources
+            .open("ITEMS\\items\\sword.tex")
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, data);
+
+        #[cfg(feature = "mmap")]
+        {
+            // SAFETY: the file is not modified while mapped
+            let archive = unsafe { Archive::open_mmap(&path).unwrap() };
+            let mut streamed = vec![];
+            archive
+                .open_entry("Items/Sword.tex")
+                .unwrap()
+                .read_to_end(&mut streamed)
+                .unwrap();
+            assert_eq!(streamed, data);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    fn check_seeks<R: Read + Seek>(mut reader: R, data: &[u8]) {
+        let mut buf = [0u8; 16];
+        // Across the boundary of the first two 256 KiB blocks
+        let boundary = 256 * 1024;
+        assert_eq!(reader.seek(SeekFrom::Start(boundary - 8)).unwrap(), boundary - 8);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[boundary as usize - 8..][..16]);
+        // Back into the first block, relative to the position
+        assert_eq!(
+            reader.seek(SeekFrom::Current(-100_000)).unwrap(),
+            boundary + 8 - 100_000
+        );
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[(boundary + 8 - 100_000) as usize..][..16]);
+
+        let len = data.len() as u64;
+        assert_eq!(reader.seek(SeekFrom::End(-5)).unwrap(), len - 5);
+        let mut tail = vec![];
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, data[data.len() - 5..]);
+
+        assert_eq!(reader.seek(SeekFrom::End(10)).unwrap(), len + 10);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.seek(SeekFrom::Start(len + 1)).unwrap(), len + 1);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        let err = reader.seek(SeekFrom::End(-(len as i64) - 1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(reader.seek(SeekFrom::Start(0)).unwrap(), 0);
+        let mut all = vec![];
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, data);
+    }
+
+    #[test]
+    fn seek_within_entries() {
+        let data = (0..600_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut writer = ArchiveWriter::new();
+        writer.add("sword.tex", data.clone(), SystemTime::UNIX_EPOCH);
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let mut archive = Archive::from_reader(Cursor::new(bytes.clone())).unwrap();
+        check_seeks(archive.open_entry("sword.tex").unwrap(), &data);
+
+        let path = std::env::temp_dir().join(format!("lib_gddb_reader_seek_{}.arc", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let archive = Archive::open_shared(&path).unwrap();
+        check_seeks(archive.open_entry("sword.tex").unwrap(), &data);
+        fs::remove_file(path).unwrap();
+    }
+}
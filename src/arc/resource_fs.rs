@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Seek};
+
+use super::{Archive, EntryReader};
+#[cfg(feature = "mmap")]
+use crate::read_at::MappedFile;
+use crate::read_at::SharedFile;
+use crate::{Error, Result};
+
+struct Mount<T> {
+    prefix: String,
+    archive: Archive<T>,
+    // Normalized path below the prefix to the entry's name in the archive
+    paths: HashMap<String, String>,
+}
+
+/// Several archives mounted under path prefixes, where an archive mounted later overrides the files of those
+/// mounted before it.
+///
+/// Paths are matched without regard to ASCII case, and `\` and `/` both separate folders.
+pub struct ResourceFs<T> {
+    mounts: Vec<Mount<T>>,
+}
+
+impl<T> Default for ResourceFs<T> {
+    fn default() -> Self {
+        Self { mounts: vec![] }
+    }
+}
+
+impl<T> ResourceFs<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts `archive` under `prefix`, on top of the archives already mounted. An empty prefix mounts it at the
+    /// root.
+    pub fn mount(&mut self, prefix: &str, archive: Archive<T>) {
+        let paths = archive
+            .index
+            .keys()
+            .map(|name| (normalize_path(name), name.clone()))
+            .collect();
+        self.mounts.push(Mount {
+            prefix: normalize_path(prefix),
+            archive,
+            paths,
+        });
+    }
+
+    /// Returns the mount prefixes and archives, bottom mount first.
+    pub fn mounts(&self) -> impl Iterator<Item = (&str, &Archive<T>)> + '_ {
+        self.mounts.iter().map(|mount| (mount.prefix.as_str(), &mount.archive))
+    }
+
+    /// Finds the topmost mount providing `path`, returning its index and the entry's name in its archive.
+    pub fn resolve(&self, path: &str) -> Option<(usize, &str)> {
+        let path = normalize_path(path);
+        self.mounts.iter().enumerate().rev().find_map(|(i, mount)| {
+            let rest = strip_prefix(&path, &mount.prefix)?;
+            mount.paths.get(rest).map(|name| (i, name.as_str()))
+        })
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    /// Lists the normalized path of every file, each listed once.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = self
+            .mounts
+            .iter()
+            .flat_map(|mount| mount.paths.keys().map(|path| join_path(&mount.prefix, path)))
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+
+    fn locate(&self, path: &str) -> Result<(usize, String)> {
+        self.resolve(path)
+            .map(|(i, name)| (i, name.to_string()))
+            .ok_or_else(|| Error::NotFound {
+                record: path.to_string(),
+            })
+    }
+}
+
+impl<R: BufRead + Seek> ResourceFs<R> {
+    /// Streams the file at `path` from the topmost archive providing it.
+    pub fn open(&mut self, path: &str) -> Result<EntryReader<'_, R>> {
+        let (i, name) = self.locate(path)?;
+        self.mounts[i].archive.open_entry(&name)
+    }
+
+    pub fn read(&mut self, path: &str) -> Result<Vec<u8>> {
+        let (i, name) = self.locate(path)?;
+        Ok(self.mounts[i].archive.get(&name)?.data)
+    }
+}
+
+impl ResourceFs<SharedFile> {
+    /// Streams the file at `path` from the topmost archive providing it.
+    pub fn open(&self, path: &str) -> Result<EntryReader<'_, SharedFile>> {
+        let (i, name) = self.locate(path)?;
+        self.mounts[i].archive.open_entry(&name)
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let (i, name) = self.locate(path)?;
+        Ok(self.mounts[i].archive.get(&name)?.data)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl ResourceFs<MappedFile> {
+    /// Streams the file at `path` from the topmost archive providing it.
+    pub fn open(&self, path: &str) -> Result<EntryReader<'_, MappedFile>> {
+        let (i, name) = self.locate(path)?;
+        self.mounts[i].archive.open_entry(&name)
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let (i, name) = self.locate(path)?;
+        Ok(self.mounts[i].archive.get(&name)?.data)
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .map(|segment| segment.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn strip_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        return Some(path);
+    }
+    path.strip_prefix(prefix)?.strip_prefix('/')
+}
+
+fn join_path(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() {
+        path.to_string()
+    } else {
+        format!("{prefix}/{path}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::arc::ArchiveWriter;
+
+    fn archive(files: &[(&str, &str)]) -> Archive<Cursor<Vec<u8>>> {
+        let mut writer = ArchiveWriter::new();
+        for &(name, data) in files {
+            writer.add(name, data.as_bytes().to_vec(), SystemTime::UNIX_EPOCH);
+        }
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+        Archive::from_reader(Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(normalize_path("Items\\Gear/Sword.TEX"), "items/gear/sword.tex");
+        assert_eq!(normalize_path("/items//./sword.tex\\"), "items/sword.tex");
+        assert_eq!(normalize_path(""), "");
+        assert_eq!(strip_prefix("items/sword.tex", "items"), Some("sword.tex"));
+        assert_eq!(strip_prefix("itemsx/sword.tex", "items"), None);
+        assert_eq!(strip_prefix("sword.tex", ""), Some("sword.tex"));
+        assert_eq!(join_path("", "sword.tex"), "sword.tex");
+        assert_eq!(join_path("items", "sword.tex"), "items/sword.tex");
+    }
+
+    #[test]
+    fn later_mounts_win() {
+        let mut resources = ResourceFs::new();
+        resources.mount(
+            "Items",
+            archive(&[("Gear\\Sword.tex", "base"), ("gear/shield.tex", "shield")]),
+        );
+        resources.mount("", archive(&[("ui/logo.tex", "logo")]));
+        resources.mount("items", archive(&[("gear/sword.tex", "mod")]));
+
+        assert_eq!(resources.resolve("ITEMS\\GEAR\\SWORD.TEX"), Some((2, "gear/sword.tex")));
+        assert_eq!(resources.resolve("items/gear/shield.tex"), Some((0, "gear/shield.tex")));
+        assert_eq!(resources.resolve("/UI//logo.tex"), Some((1, "ui/logo.tex")));
+        assert_eq!(resources.resolve("gear/sword.tex"), None);
+        assert!(!resources.contains("items/gear/axe.tex"));
+
+        assert_eq!(resources.read("items/gear/sword.tex").unwrap(), b"mod");
+        assert_eq!(resources.read("Items/Gear/Shield.tex").unwrap(), b"shield");
+        assert!(matches!(resources.read("items/axe.tex"), Err(Error::NotFound { .. })));
+        assert_eq!(
+            resources.paths(),
+            ["items/gear/shield.tex", "items/gear/sword.tex", "ui/logo.tex"]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::{StorageType, ARC_MAGIC, HEADER_LEN, SUPPORTED_VERSIONS};
+use crate::error::check_limit;
+use crate::filetime::to_filetime;
+use crate::Result;
+
+// Files are split into blocks of at most this many bytes before compression
+const CHUNK_LEN: usize = 256 * 1024;
+
+struct PendingEntry {
+    name: String,
+    data: Vec<u8>,
+    last_modified_at: SystemTime,
+}
+
+#[derive(Default)]
+pub struct ArchiveWriter {
+    entries: Vec<PendingEntry>,
+    index: HashMap<String, usize>,
+}
+
+impl ArchiveWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry, replacing any earlier entry with the same name.
+    pub fn add<S: Into<String>>(&mut self, name: S, data: Vec<u8>, last_modified_at: SystemTime) {
+        let entry = PendingEntry {
+            name: name.into(),
+            data,
+            last_modified_at,
+        };
+        match self.index.get(&entry.name) {
+            Some(&i) => self.entries[i] = entry,
+            None => {
+                self.index.insert(entry.name.clone(), self.entries.len());
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    pub fn add_file<S: Into<String>, P: AsRef<Path>>(&mut self, name: S, path: P) -> Result<()> {
+        let data = std::fs::read(&path)?;
+        let last_modified_at = std::fs::metadata(&path)?.modified()?;
+        self.add(name, data, last_modified_at);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn write<W: Write>(&self, mut out: W) -> Result<()> {
+        let mut data = vec![];
+        let mut blocks = vec![];
+        let mut names = vec![];
+        let mut metadata = vec![];
+        let mut block_count = 0usize;
+        for entry in self.entries.iter() {
+            let name = Some(entry.name.as_str());
+            let first_block = block_count;
+            let offset = HEADER_LEN as usize + data.len();
+            let mut storage = StorageType::Stored;
+            for chunk in entry.data.chunks(CHUNK_LEN) {
+                let block_offset = check_limit(name, "block offset", HEADER_LEN as usize + data.len())?;
+                let compressed = lz4::block::compress(chunk, None, false)?;
+                // The reader treats equal lengths as stored data, so only keep compression when it helps
+                let stored = if compressed.len() < chunk.len() {
+                    storage = StorageType::Lz4;
+                    &compressed[..]
+                } else {
+                    chunk
+                };
+                blocks.extend_from_slice(&block_offset.to_le_bytes());
+                blocks.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+                blocks.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                data.extend_from_slice(stored);
+                block_count += 1;
+            }
+            let compressed_len = HEADER_LEN as usize + data.len() - offset;
+
+            let name_offset = check_limit(name, "record list length", names.len())?;
+            names.extend_from_slice(entry.name.as_bytes());
+            names.push(0);
+
+            metadata.extend_from_slice(&u32::from(storage).to_le_bytes());
+            metadata.extend_from_slice(&check_limit(name, "block offset", offset)?.to_le_bytes());
+            metadata.extend_from_slice(&check_limit(name, "compressed length", compressed_len)?.to_le_bytes());
+            metadata.extend_from_slice(&check_limit(name, "uncompressed length", entry.data.len())?.to_le_bytes());
+            metadata.extend_from_slice(&0u32.to_le_bytes()); // unknown field
+            metadata.extend_from_slice(&to_filetime(entry.last_modified_at).to_le_bytes());
+            metadata.extend_from_slice(&check_limit(name, "block count", block_count - first_block)?.to_le_bytes());
+            metadata.extend_from_slice(&check_limit(name, "block index", first_block)?.to_le_bytes());
+            metadata.extend_from_slice(&check_limit(name, "name length", entry.name.len())?.to_le_bytes());
+            metadata.extend_from_slice(&name_offset.to_le_bytes());
+        }
+
+        let block_list_offset = check_limit(None, "file length", HEADER_LEN as usize + data.len())?;
+        let file_len = block_list_offset as usize + blocks.len() + names.len() + metadata.len();
+        check_limit(None, "file length", file_len)?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(&ARC_MAGIC.to_le_bytes());
+        header.extend_from_slice(&SUPPORTED_VERSIONS[SUPPORTED_VERSIONS.len() - 1].to_le_bytes());
+        header.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(block_count as u32).to_le_bytes());
+        header.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        header.extend_from_slice(&block_list_offset.to_le_bytes());
+
+        for section in [&header, &data, &blocks, &names, &metadata] {
+            out.write_all(&section[..])?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::arc::Archive;
+
+    // Bytes lz4 cannot shrink, so they are written as stored blocks
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let last_modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let entries = [
+            ("text/tags.txt", b"tagA=Sword\n".repeat(100), StorageType::Lz4),
+            ("noise.bin", noise(64), StorageType::Stored),
+            ("empty.txt", vec![], StorageType::Stored),
+            (
+                "large.bin",
+                [b"x".repeat(CHUNK_LEN), noise(1000)].concat(),
+                StorageType::Lz4,
+            ),
+        ];
+        let mut writer = ArchiveWriter::new();
+        for (name, data, _) in entries.iter() {
+            writer.add(*name, data.clone(), last_modified_at);
+        }
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let mut archive = Archive::parse(&bytes[..]).unwrap();
+        let listed = archive.entries();
+        assert_eq!(listed.len(), entries.len());
+        for ((name, data, storage), entry) in entries.iter().zip(listed) {
+            assert_eq!(entry.name, *name);
+            assert_eq!(entry.storage, *storage);
+            assert_eq!(entry.uncompressed_len as usize, data.len());
+            assert_eq!(entry.block_count as usize, data.len().div_ceil(CHUNK_LEN));
+            assert_eq!(entry.last_modified_at, last_modified_at);
+            assert_eq!(archive.get(name).unwrap().data, *data);
+
+            let mut streamed = vec![];
+            archive.open_entry(name).unwrap().read_to_end(&mut streamed).unwrap();
+            assert_eq!(streamed, *data);
+        }
+    }
+
+    #[test]
+    fn add_replaces_entry_with_same_name() {
+        let mut writer = ArchiveWriter::new();
+        writer.add("a.txt", b"old".to_vec(), SystemTime::UNIX_EPOCH);
+        writer.add("a.txt", b"new".to_vec(), SystemTime::UNIX_EPOCH);
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let mut archive = Archive::parse(&bytes[..]).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive.get("a.txt").unwrap().data, b"new");
+    }
+}
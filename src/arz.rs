@@ -1,18 +1,46 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Cursor, Result, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, Cursor, Seek, SeekFrom};
 use std::path::Path;
 
+use crate::adler32::Adler32;
 use crate::buf_read_ext::BufReadExt;
+use crate::error::{check_bounds, check_length, check_plausible};
+use crate::read_at::{ReadAt, SharedFile};
+#[cfg(feature = "mmap")]
+use crate::MappedFile;
+use crate::{Error, Result};
+
+mod layered;
+#[cfg(feature = "rayon")]
+mod par;
+mod writer;
+
+pub use layered::LayeredDatabase;
+pub use writer::DatabaseWriter;
 
 const ARZ_MAGIC: u16 = 2;
 const SUPPORTED_VERSIONS: [u16; 1] = [3];
+const HEADER_LEN: u64 = 24;
+// string index, kind length, offset, compressed and uncompressed lengths, timestamp
+const MIN_RAW_RECORD_LEN: u64 = 28;
+const FOOTER_LEN: u64 = 16;
+const CHECKSUM_CHUNK_LEN: u64 = 1 << 20;
+
+// Owned copies of the strings, or their spans in a memory map, checked to be UTF-8 when opening
+enum Strings {
+    Owned(Vec<String>),
+    #[cfg(feature = "mmap")]
+    Mapped(MappedFile, Vec<(u32, u32)>),
+}
 
 #[allow(dead_code)]
 pub struct Database<T> {
     file: T,
-    strings: Vec<String>,
+    strings: Strings,
+    index: HashMap<String, RawRecord>,
+    file_len: u64,
     head: u16,
     version: u16,
     record_count: u32,
@@ -22,6 +50,25 @@ pub struct Database<T> {
     string_table_len: u32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumRegion {
+    File,
+    RecordTable,
+    RecordData,
+    StringTable,
+}
+
+impl fmt::Display for ChecksumRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File => write!(f, "file"),
+            Self::RecordTable => write!(f, "record table"),
+            Self::RecordData => write!(f, "record data"),
+            Self::StringTable => write!(f, "string table"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RawRecord {
     string_index: u32,
@@ -31,7 +78,7 @@ pub struct RawRecord {
     uncompressed_len: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Record {
     pub id: String,
     pub kind: String,
@@ -178,31 +225,44 @@ impl From<Vec<bool>> for DatabaseValue {
     }
 }
 
-impl<R: BufRead + Seek> Database<R> {
-    pub fn resolve(&mut self, raw: RawRecord) -> Result<Record> {
-        let id = self.lookup_str(raw.string_index as usize)?;
-        let kind = raw.kind;
-        let mut compressed = vec![0u8; raw.compressed_len as usize];
+impl<T> Database<T> {
+    fn decode(&self, raw: RawRecord, compressed: &[u8]) -> Result<Record> {
+        let id = self.record_id(&raw)?;
+        let offset = raw.offset as u64 + HEADER_LEN;
         let mut data = vec![0u8; raw.uncompressed_len as usize];
-
-        let _ = self.file.seek(SeekFrom::Start(raw.offset as u64 + 24));
-        let _ = self.file.read_exact(&mut compressed);
-
-        lz4::block::decompress_to_buffer(&compressed[..], Some(raw.uncompressed_len as i32), &mut data[..])?;
-
-        let data = self.resolve_inner(&data[..])?;
-
-        Ok(Record { id, kind, data })
+        let len = lz4::block::decompress_to_buffer(compressed, Some(raw.uncompressed_len as i32), &mut data[..])
+            .map_err(|source| Error::Lz4 {
+                record: id.clone(),
+                offset,
+                source,
+            })?;
+        check_length(Some(&id), offset, raw.uncompressed_len as u64, len as u64)?;
+        let data = self.resolve_inner(&id, &data[..])?;
+        Ok(Record {
+            id,
+            kind: raw.kind,
+            data,
+        })
     }
 
-    fn resolve_inner(&self, data: &[u8]) -> Result<HashMap<String, DatabaseValue>> {
+    fn resolve_inner(&self, id: &str, data: &[u8]) -> Result<HashMap<String, DatabaseValue>> {
         let mut result = HashMap::default();
         let mut buf = Cursor::new(data);
+        let lookup_str = |index: u32, offset: u64| {
+            self.lookup_str(index)
+                .map(|s| s.to_string())
+                .ok_or_else(|| Error::CorruptStringIndex {
+                    record: Some(id.to_string()),
+                    offset,
+                    index,
+                })
+        };
         while buf.position() < data.len() as u64 {
+            let offset = buf.position();
             let kind = buf.read_u16()?;
             let entry_count = buf.read_u16()?;
             let str_index = buf.read_u32()?;
-            let str = self.lookup_str(str_index as usize)?;
+            let str = lookup_str(str_index, offset + 4)?;
             let value = match kind {
                 0 => (0..entry_count)
                     .map(|_| Ok(buf.read_u32()?))
@@ -213,51 +273,206 @@ impl<R: BufRead + Seek> Database<R> {
                     .collect::<Result<Vec<_>>>()?
                     .into(),
                 2 => (0..entry_count)
-                    .map(|_| self.lookup_str(buf.read_u32()? as usize))
+                    .map(|_| {
+                        let offset = buf.position();
+                        lookup_str(buf.read_u32()?, offset)
+                    })
                     .collect::<Result<Vec<_>>>()?
                     .into(),
                 3 => (0..entry_count)
                     .map(|_| {
-                        Ok(match buf.read_u32()? {
-                            0 => false,
-                            1 => true,
-                            _ => unreachable!(),
-                        })
+                        let offset = buf.position();
+                        match buf.read_u32()? {
+                            0 => Ok(false),
+                            1 => Ok(true),
+                            value => Err(Error::BadBool {
+                                record: id.to_string(),
+                                offset,
+                                value,
+                            }),
+                        }
                     })
                     .collect::<Result<Vec<_>>>()?
                     .into(),
-                _ => panic!("Unexpected database value kind {kind}"),
+                _ => {
+                    return Err(Error::BadValueKind {
+                        record: id.to_string(),
+                        offset,
+                        kind,
+                    })
+                }
             };
-            result.insert(str.clone(), value);
+            result.insert(str, value);
         }
         Ok(result)
     }
 
-    fn lookup_str(&self, index: usize) -> Result<String> {
-        self.strings
-            .get(index as usize)
-            .map(|s| s.clone())
-            .ok_or_else(|| std::io::Error::other(format!("Failed to resolve string id {}", index)))
+    fn lookup_str(&self, index: u32) -> Option<&str> {
+        match &self.strings {
+            Strings::Owned(strings) => strings.get(index as usize).map(|s| s.as_str()),
+            #[cfg(feature = "mmap")]
+            Strings::Mapped(map, spans) => {
+                let &(offset, len) = spans.get(index as usize)?;
+                let bytes = &map.as_ref()[offset as usize..offset as usize + len as usize];
+                // SAFETY: every span was checked to be UTF-8 when opening, and the map does not change after
+                Some(unsafe { std::str::from_utf8_unchecked(bytes) })
+            }
+        }
     }
 
     pub fn record_id(&self, raw: &RawRecord) -> Result<String> {
-        self.lookup_str(raw.string_index as usize)
+        self.lookup_str(raw.string_index)
+            .map(|s| s.to_string())
+            .ok_or_else(|| Error::CorruptStringIndex {
+                record: None,
+                offset: raw.offset as u64 + HEADER_LEN,
+                index: raw.string_index,
+            })
+    }
+
+    pub fn get_raw(&self, id: &str) -> Option<&RawRecord> {
+        self.index.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn raw_records(&self) -> impl Iterator<Item = &RawRecord> + '_ {
+        self.index.values()
+    }
+
+    fn footer_offset(&self) -> u64 {
+        self.string_table_offset as u64 + self.string_table_len as u64
+    }
+
+    // The footer lists the file, record table, record data and string table checksums, but the whole file is
+    // checked last so that a mismatch names the corrupt region
+    fn checksum_regions(&self, footer: [u32; 4]) -> [(ChecksumRegion, u32, u64, u64); 4] {
+        let records_offset = self.records_offset as u64;
+        [
+            (
+                ChecksumRegion::RecordTable,
+                footer[1],
+                records_offset,
+                self.records_len as u64,
+            ),
+            (
+                ChecksumRegion::RecordData,
+                footer[2],
+                HEADER_LEN,
+                records_offset - HEADER_LEN,
+            ),
+            (
+                ChecksumRegion::StringTable,
+                footer[3],
+                self.string_table_offset as u64,
+                self.string_table_len as u64,
+            ),
+            (ChecksumRegion::File, footer[0], 0, self.footer_offset()),
+        ]
+    }
+
+    fn with_file<U>(self, file: U) -> Database<U> {
+        Database {
+            file,
+            strings: self.strings,
+            index: self.index,
+            file_len: self.file_len,
+            head: self.head,
+            version: self.version,
+            record_count: self.record_count,
+            records_offset: self.records_offset,
+            records_len: self.records_len,
+            string_table_offset: self.string_table_offset,
+            string_table_len: self.string_table_len,
+        }
+    }
+}
+
+// Finds the offset and length of every string of the string table `table` found at `table_offset`
+fn string_spans(table: &[u8], table_offset: u32) -> Result<Vec<(u32, u32)>> {
+    let end = table_offset as u64 + table.len() as u64;
+    let mut buf = table;
+    let position = |buf: &[u8]| end - buf.len() as u64;
+    let mut spans = vec![];
+    while !buf.is_empty() {
+        let count = buf.read_u32()?;
+        // Each string is at least its 4 byte length
+        check_bounds(None, position(buf), count as u64 * 4, end)?;
+        spans.reserve(count as usize);
+        for _ in 0..count {
+            let len = buf.read_u32()?;
+            let offset = position(buf);
+            check_bounds(None, offset, len as u64, end)?;
+            let (bytes, rest) = buf.split_at(len as usize);
+            std::str::from_utf8(bytes).map_err(|_| Error::NonUtf8Name { record: None, offset })?;
+            spans.push((offset as u32, len));
+            buf = rest;
+        }
+    }
+    Ok(spans)
+}
+
+fn check_checksum(region: ChecksumRegion, offset: u64, expected: u32, actual: u32) -> Result<()> {
+    if actual != expected {
+        return Err(Error::ChecksumMismatch {
+            region,
+            offset,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+impl<R: BufRead + Seek> Database<R> {
+    pub fn resolve(&mut self, raw: RawRecord) -> Result<Record> {
+        let mut compressed = vec![0u8; raw.compressed_len as usize];
+        self.file.seek(SeekFrom::Start(raw.offset as u64 + HEADER_LEN))?;
+        self.file.read_exact(&mut compressed)?;
+        self.decode(raw, &compressed[..])
+    }
+
+    pub fn get(&mut self, id: &str) -> Result<Record> {
+        let raw = self
+            .get_raw(id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        self.resolve(raw)
     }
 
     pub fn iter_records(&mut self) -> Result<impl Iterator<Item = Result<RawRecord>> + '_> {
-        let _ = self.file.seek(SeekFrom::Start(self.records_offset as u64))?;
+        self.file.seek(SeekFrom::Start(self.records_offset as u64))?;
         Ok((0..self.record_count).map(|_| {
             let string_index = self.file.read_u32()?;
+            let record = self.lookup_str(string_index).map(|s| s.to_string());
             let kind_len = self.file.read_u32()?;
+            let kind_offset = self.file.stream_position()?;
+            check_bounds(record.as_deref(), kind_offset, kind_len as u64, self.file_len)?;
             let mut buf = vec![0u8; kind_len as usize];
-            let _ = self.file.read_exact(&mut buf);
+            self.file.read_exact(&mut buf)?;
             let kind = std::str::from_utf8(&buf[..])
                 .map(|s| s.to_owned())
-                .map_err(|_| std::io::Error::other(format!("Found non-utf8 bytes in record kind")))?;
+                .map_err(|_| Error::NonUtf8Name {
+                    record: record.clone(),
+                    offset: kind_offset,
+                })?;
             let offset = self.file.read_u32()?;
             let compressed_size = self.file.read_u32()?;
             let uncompressed_size = self.file.read_u32()?;
-            let _ = self.file.seek(SeekFrom::Current(8))?;
+            self.file.seek(SeekFrom::Current(8))?;
+            let data_offset = offset as u64 + HEADER_LEN;
+            check_bounds(record.as_deref(), data_offset, compressed_size as u64, self.file_len)?;
+            check_plausible(record.as_deref(), data_offset, compressed_size, uncompressed_size)?;
             Ok(RawRecord {
                 string_index,
                 kind,
@@ -268,46 +483,114 @@ impl<R: BufRead + Seek> Database<R> {
         }))
     }
 
-    fn build_string_table(mut self) -> Result<Self> {
-        let _ = self.file.seek(SeekFrom::Start(self.string_table_offset as u64))?;
-        let end = self.string_table_offset as u64 + self.string_table_len as u64;
-        let mut table = vec![];
-        while self.file.stream_position()? < end {
-            let count = self.file.read_u32()?;
-            table.extend(
-                (0..count)
-                    .map(|_| {
-                        let len = self.file.read_u32()?;
-                        let mut buf = vec![0u8; len as usize];
-                        let _ = self.file.read_exact(&mut buf);
-                        std::str::from_utf8(&buf[..])
-                            .map(|s| s.to_owned())
-                            .map_err(|_| std::io::Error::other(format!("Found non-utf8 bytes in record name")))
-                    })
-                    .collect::<Result<Vec<_>>>()?,
-            )
+    pub fn verify(&mut self) -> Result<()> {
+        let footer_offset = self.footer_offset();
+        check_bounds(None, footer_offset, FOOTER_LEN, self.file_len)?;
+        self.file.seek(SeekFrom::Start(footer_offset))?;
+        let mut footer = [0u32; 4];
+        for checksum in footer.iter_mut() {
+            *checksum = self.file.read_u32()?;
+        }
+        for (region, expected, offset, len) in self.checksum_regions(footer) {
+            let actual = self.checksum(offset, len)?;
+            check_checksum(region, offset, expected, actual)?;
         }
-        self.strings = table.into();
+        Ok(())
+    }
+
+    fn checksum(&mut self, offset: u64, len: u64) -> Result<u32> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut hasher = Adler32::default();
+        let mut remaining = len;
+        while remaining > 0 {
+            let buf = self.file.fill_buf()?;
+            if buf.is_empty() {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            let n = buf.len().min(remaining as usize);
+            hasher.update(&buf[..n]);
+            self.file.consume(n);
+            remaining -= n as u64;
+        }
+        Ok(hasher.finish())
+    }
+
+    fn read_strings(&mut self) -> Result<Vec<String>> {
+        let mut table = vec![0u8; self.string_table_len as usize];
+        self.file.seek(SeekFrom::Start(self.string_table_offset as u64))?;
+        self.file.read_exact(&mut table)?;
+        let base = self.string_table_offset as usize;
+        Ok(string_spans(&table, self.string_table_offset)?
+            .into_iter()
+            .map(|(offset, len)| {
+                let start = offset as usize - base;
+                // Already checked to be UTF-8
+                String::from_utf8_lossy(&table[start..start + len as usize]).into_owned()
+            })
+            .collect())
+    }
+
+    fn build_index(mut self) -> Result<Self> {
+        let raw_records = self.iter_records()?.collect::<Result<Vec<_>>>()?;
+        let mut index = HashMap::with_capacity(raw_records.len());
+        for raw in raw_records.into_iter() {
+            index.insert(self.record_id(&raw)?, raw);
+        }
+        self.index = index;
         Ok(self)
     }
 
-    fn from(mut buf: R) -> Result<Self> {
+    /// Reads from any seekable source whose data starts at offset 0.
+    pub fn from_reader(buf: R) -> Result<Self> {
+        let mut database = Self::read_header(buf)?;
+        database.strings = Strings::Owned(database.read_strings()?);
+        database.build_index()
+    }
+
+    fn read_header(mut buf: R) -> Result<Self> {
+        let file_len = buf.seek(SeekFrom::End(0))?;
+        buf.seek(SeekFrom::Start(0))?;
         let head = buf.read_u16()?;
         let version = buf.read_u16()?;
         if head != ARZ_MAGIC {
-            return Err(std::io::Error::other(format!("Unexpected magic number {head}")));
+            return Err(Error::BadMagic {
+                offset: 0,
+                found: head as u32,
+            });
         }
         if !SUPPORTED_VERSIONS.contains(&version) {
-            return Err(std::io::Error::other(format!("Unsupported version {version}")));
+            return Err(Error::UnsupportedVersion {
+                offset: 2,
+                version: version as u32,
+            });
         }
         let records_offset = buf.read_u32()?;
         let records_len = buf.read_u32()?;
         let record_count = buf.read_u32()?;
         let string_table_offset = buf.read_u32()?;
         let string_table_len = buf.read_u32()?;
-        Self {
+        // Record data sits between the header and the record table
+        if (records_offset as u64) < HEADER_LEN {
+            return Err(Error::OutOfBounds {
+                record: None,
+                offset: records_offset as u64,
+                len: records_len as u64,
+                file_len,
+            });
+        }
+        check_bounds(None, records_offset as u64, records_len as u64, file_len)?;
+        check_bounds(
+            None,
+            records_offset as u64,
+            record_count as u64 * MIN_RAW_RECORD_LEN,
+            file_len,
+        )?;
+        check_bounds(None, string_table_offset as u64, string_table_len as u64, file_len)?;
+        Ok(Self {
             file: buf,
-            strings: Default::default(),
+            strings: Strings::Owned(vec![]),
+            index: Default::default(),
+            file_len,
             head,
             version,
             record_count,
@@ -315,21 +598,240 @@ impl<R: BufRead + Seek> Database<R> {
             records_offset,
             string_table_offset,
             string_table_len,
-        }
-        .build_string_table()
+        })
     }
 }
 
-impl Database<Cursor<&'static [u8]>> {
-    pub fn parse(bytes: &'static [u8]) -> Result<Self> {
+impl<B: AsRef<[u8]>> Database<Cursor<B>> {
+    /// Reads from an in-memory buffer, borrowed (`&[u8]`) or owned (`Vec<u8>`, `Arc<[u8]>`, ...).
+    pub fn parse(bytes: B) -> Result<Self> {
         let read = Cursor::new(bytes);
-        Self::from(read)
+        Self::from_reader(read)
     }
 }
 
 impl Database<BufReader<File>> {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = BufReader::new(File::open(path)?);
-        Self::from(file)
+        Self::from_reader(file)
+    }
+
+    pub fn open_strict<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut database = Self::open(path)?;
+        database.verify()?;
+        Ok(database)
+    }
+}
+
+impl<S: ReadAt> Database<S> {
+    fn resolve_at(&self, raw: RawRecord) -> Result<Record> {
+        let compressed = self
+            .file
+            .read_at(raw.offset as u64 + HEADER_LEN, raw.compressed_len as usize)?;
+        self.decode(raw, &compressed[..])
+    }
+
+    fn get_at(&self, id: &str) -> Result<Record> {
+        let raw = self
+            .get_raw(id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        self.resolve_at(raw)
+    }
+
+    fn verify_at(&self) -> Result<()> {
+        let footer_offset = self.footer_offset();
+        check_bounds(None, footer_offset, FOOTER_LEN, self.file_len)?;
+        let bytes = self.file.read_at(footer_offset, FOOTER_LEN as usize)?;
+        let mut buf = &bytes[..];
+        let mut footer = [0u32; 4];
+        for checksum in footer.iter_mut() {
+            *checksum = buf.read_u32()?;
+        }
+        for (region, expected, offset, len) in self.checksum_regions(footer) {
+            let mut hasher = Adler32::default();
+            let mut position = offset;
+            while position < offset + len {
+                let n = (offset + len - position).min(CHECKSUM_CHUNK_LEN) as usize;
+                hasher.update(&self.file.read_at(position, n)?[..]);
+                position += n as u64;
+            }
+            check_checksum(region, offset, expected, hasher.finish())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Database<MappedFile> {
+    /// Opens a database through a memory map, so records can be resolved through a shared reference. Strings are
+    /// read in place from the map rather than copied, though the id index is still built on open.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the database is open, see [`MappedFile::open`].
+    pub unsafe fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        // SAFETY: upheld by the caller
+        let map = unsafe { MappedFile::open(path)? };
+        let mut database = Database::read_header(Cursor::new(map.as_ref()))?;
+        let table = &map.as_ref()[database.string_table_offset as usize..database.footer_offset() as usize];
+        let spans = string_spans(table, database.string_table_offset)?;
+        database.strings = Strings::Mapped(map.clone(), spans);
+        let database = database.build_index()?.with_file(());
+        Ok(database.with_file(map))
+    }
+
+    pub fn resolve(&self, raw: RawRecord) -> Result<Record> {
+        self.resolve_at(raw)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Record> {
+        self.get_at(id)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        self.verify_at()
+    }
+}
+
+impl Database<SharedFile> {
+    /// Opens a database that can be shared between threads, each resolving records through its own positional
+    /// reads.
+    pub fn open_shared<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = SharedFile::open(path)?;
+        let database = Database::from_reader(BufReader::new(file.try_clone()?))?;
+        Ok(database.with_file(file))
+    }
+
+    pub fn resolve(&self, raw: RawRecord) -> Result<Record> {
+        self.resolve_at(raw)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Record> {
+        self.get_at(id)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        self.verify_at()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adler32::adler32;
+
+    // Assembles a database with a single record by hand rather than with `DatabaseWriter`, so the footer layout is
+    // checked independently of the writer: version 3 footers hold the Adler-32 of the file up to the footer, then
+    // of the record table, the record data and the string table, in that order.
+    fn fixture() -> Vec<u8> {
+        let strings = ["records/a.dbr", "value"];
+        let mut uncompressed = vec![];
+        uncompressed.extend_from_slice(&0u16.to_le_bytes());
+        uncompressed.extend_from_slice(&1u16.to_le_bytes());
+        uncompressed.extend_from_slice(&1u32.to_le_bytes());
+        uncompressed.extend_from_slice(&42u32.to_le_bytes());
+        let data = lz4::block::compress(&uncompressed, None, false).unwrap();
+
+        let kind = b"ItemArtifact";
+        let mut table = vec![];
+        table.extend_from_slice(&0u32.to_le_bytes());
+        table.extend_from_slice(&(kind.len() as u32).to_le_bytes());
+        table.extend_from_slice(kind);
+        table.extend_from_slice(&0u32.to_le_bytes());
+        table.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        table.extend_from_slice(&(uncompressed.len() as u32).to_le_bytes());
+        table.extend_from_slice(&0u64.to_le_bytes());
+
+        let mut string_table = vec![];
+        string_table.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        for s in strings {
+            string_table.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            string_table.extend_from_slice(s.as_bytes());
+        }
+
+        let records_offset = HEADER_LEN as u32 + data.len() as u32;
+        let mut file = vec![];
+        file.extend_from_slice(&ARZ_MAGIC.to_le_bytes());
+        file.extend_from_slice(&3u16.to_le_bytes());
+        file.extend_from_slice(&records_offset.to_le_bytes());
+        file.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&(records_offset + table.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(string_table.len() as u32).to_le_bytes());
+        file.extend_from_slice(&data);
+        file.extend_from_slice(&table);
+        file.extend_from_slice(&string_table);
+        for checksum in [adler32(&file), adler32(&table), adler32(&data), adler32(&string_table)] {
+            file.extend_from_slice(&checksum.to_le_bytes());
+        }
+        file
+    }
+
+    fn mismatched_region(bytes: &[u8]) -> Option<ChecksumRegion> {
+        match Database::parse(bytes).unwrap().verify() {
+            Ok(()) => None,
+            Err(Error::ChecksumMismatch { region, .. }) => Some(region),
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn verify_accepts_footer_in_file_table_data_strings_order() {
+        let bytes = fixture();
+        assert_eq!(mismatched_region(&bytes), None);
+        let mut db = Database::parse(&bytes[..]).unwrap();
+        assert_eq!(db.get("records/a.dbr").unwrap().data["value"], DatabaseValue::Int(42));
+    }
+
+    #[test]
+    fn read_string_table() {
+        let bytes = fixture();
+        let mut db = Database::parse(&bytes[..]).unwrap();
+        assert_eq!(db.lookup_str(1), Some("value"));
+        assert_eq!(db.lookup_str(2), None);
+        assert_eq!(db.get("records/a.dbr").unwrap().data["value"], DatabaseValue::Int(42));
+
+        #[cfg(feature = "mmap")]
+        {
+            let path = std::env::temp_dir().join(format!("lib_gddb_strings_{}.arz", std::process::id()));
+            std::fs::write(&path, &bytes).unwrap();
+            // SAFETY: the file is not modified while mapped
+            let db = unsafe { Database::open_mmap(&path).unwrap() };
+            assert!(matches!(db.strings, Strings::Mapped(..)));
+            assert_eq!(db.lookup_str(1), Some("value"));
+            assert_eq!(db.get("records/a.dbr").unwrap().data["value"], DatabaseValue::Int(42));
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let mut corrupt = bytes.clone();
+        corrupt[db.string_table_offset as usize + 8] = 0xFF;
+        assert!(matches!(Database::parse(&corrupt[..]), Err(Error::NonUtf8Name { .. })));
+    }
+
+    #[test]
+    fn verify_names_corrupt_region() {
+        let bytes = fixture();
+        let db = Database::parse(&bytes[..]).unwrap();
+        let regions = [
+            (ChecksumRegion::RecordData, HEADER_LEN as usize),
+            (ChecksumRegion::RecordTable, db.records_offset as usize + 8),
+            (ChecksumRegion::StringTable, db.string_table_offset as usize + 8),
+        ];
+        for (region, offset) in regions {
+            let mut corrupt = bytes.clone();
+            corrupt[offset] ^= 1;
+            assert_eq!(mismatched_region(&corrupt), Some(region));
+        }
+
+        // Swapping the record table and record data checksums must be noticed
+        let footer = bytes.len() - FOOTER_LEN as usize;
+        let mut swapped = bytes.clone();
+        swapped[footer + 4..footer + 12].rotate_left(4);
+        assert_eq!(mismatched_region(&swapped), Some(ChecksumRegion::RecordTable));
+
+        let mut corrupt = bytes.clone();
+        corrupt[footer] ^= 0xFF;
+        assert_eq!(mismatched_region(&corrupt), Some(ChecksumRegion::File));
     }
 }
@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::io::{BufRead, Seek};
+
+use super::{Database, Record};
+#[cfg(feature = "mmap")]
+use crate::read_at::MappedFile;
+use crate::read_at::SharedFile;
+use crate::{Error, Result};
+
+/// A stack of databases, such as the base game followed by its expansions and mods, where a record in a higher
+/// layer overrides any record with the same id below it.
+pub struct LayeredDatabase<T> {
+    // Bottom layer first
+    layers: Vec<(String, Database<T>)>,
+}
+
+impl<T> Default for LayeredDatabase<T> {
+    fn default() -> Self {
+        Self { layers: vec![] }
+    }
+}
+
+impl<T> LayeredDatabase<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `database` on top of the existing layers.
+    pub fn push<S: Into<String>>(&mut self, name: S, database: Database<T>) {
+        self.layers.push((name.into(), database));
+    }
+
+    /// Returns the layers' names and databases, bottom layer first.
+    pub fn layers(&self) -> impl Iterator<Item = (&str, &Database<T>)> + '_ {
+        self.layers.iter().map(|(name, database)| (name.as_str(), database))
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn layer_name(&self, layer: usize) -> Option<&str> {
+        self.layers.get(layer).map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the index of the topmost layer containing `id`, which is the one that supplies the record.
+    pub fn layer_of(&self, id: &str) -> Option<usize> {
+        self.layers.iter().rposition(|(_, database)| database.contains(id))
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.layer_of(id).is_some()
+    }
+
+    /// Returns the ids of the records in every layer, each listed once.
+    pub fn record_ids(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        let mut ids = self
+            .layers
+            .iter()
+            .flat_map(|(_, database)| database.index.keys())
+            .map(|id| id.as_str())
+            .filter(|id| seen.insert(*id))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Returns the ids of the records in `layer` that replace a record from a layer below it.
+    pub fn overrides(&self, layer: usize) -> Vec<&str> {
+        let Some((_, database)) = self.layers.get(layer) else {
+            return vec![];
+        };
+        let below = &self.layers[..layer];
+        let mut ids = database
+            .index
+            .keys()
+            .filter(|id| below.iter().any(|(_, lower)| lower.contains(id)))
+            .map(|id| id.as_str())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn top_layer_mut(&mut self, id: &str) -> Result<&mut Database<T>> {
+        let layer = self
+            .layer_of(id)
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        Ok(&mut self.layers[layer].1)
+    }
+
+    fn top_layer(&self, id: &str) -> Result<&Database<T>> {
+        let layer = self
+            .layer_of(id)
+            .ok_or_else(|| Error::NotFound { record: id.to_string() })?;
+        Ok(&self.layers[layer].1)
+    }
+}
+
+impl<R: BufRead + Seek> LayeredDatabase<R> {
+    /// Resolves `id` from the topmost layer containing it.
+    pub fn get(&mut self, id: &str) -> Result<Record> {
+        self.top_layer_mut(id)?.get(id)
+    }
+}
+
+impl LayeredDatabase<SharedFile> {
+    /// Resolves `id` from the topmost layer containing it.
+    pub fn get(&self, id: &str) -> Result<Record> {
+        self.top_layer(id)?.get(id)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl LayeredDatabase<MappedFile> {
+    /// Resolves `id` from the topmost layer containing it.
+    pub fn get(&self, id: &str) -> Result<Record> {
+        self.top_layer(id)?.get(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::arz::{DatabaseValue, DatabaseWriter};
+
+    fn layer(records: &[(&str, u32)]) -> Database<Cursor<Vec<u8>>> {
+        let mut writer = DatabaseWriter::new();
+        for &(id, level) in records {
+            writer.add(Record {
+                id: id.to_string(),
+                kind: "ItemArtifact".to_string(),
+                data: HashMap::from([("levelRequirement".to_string(), DatabaseValue::Int(level))]),
+            });
+        }
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+        Database::from_reader(Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn topmost_layer_wins() {
+        let mut database = LayeredDatabase::new();
+        database.push("base", layer(&[("records/a.dbr", 1), ("records/b.dbr", 2)]));
+        database.push("gdx1", layer(&[("records/a.dbr", 10), ("records/c.dbr", 3)]));
+        database.push("mod", layer(&[("records/c.dbr", 30)]));
+
+        let level = |database: &mut LayeredDatabase<_>, id| database.get(id).unwrap().data["levelRequirement"].clone();
+        assert_eq!(level(&mut database, "records/a.dbr"), DatabaseValue::Int(10));
+        assert_eq!(level(&mut database, "records/b.dbr"), DatabaseValue::Int(2));
+        assert_eq!(level(&mut database, "records/c.dbr"), DatabaseValue::Int(30));
+        assert!(matches!(database.get("records/d.dbr"), Err(Error::NotFound { .. })));
+
+        assert_eq!(database.layer_of("records/a.dbr"), Some(1));
+        assert_eq!(database.layer_of("records/b.dbr"), Some(0));
+        assert_eq!(database.layer_of("records/c.dbr"), Some(2));
+        assert_eq!(database.layer_of("records/d.dbr"), None);
+        assert_eq!(database.layer_name(2), Some("mod"));
+        assert_eq!(
+            database.record_ids(),
+            ["records/a.dbr", "records/b.dbr", "records/c.dbr"]
+        );
+
+        assert!(database.overrides(0).is_empty());
+        assert_eq!(database.overrides(1), ["records/a.dbr"]);
+        assert_eq!(database.overrides(2), ["records/c.dbr"]);
+        assert!(database.overrides(3).is_empty());
+    }
+}
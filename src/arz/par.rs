@@ -0,0 +1,56 @@
+use std::io::{BufRead, Seek, SeekFrom};
+
+use rayon::prelude::*;
+
+use super::{Database, RawRecord, Record, HEADER_LEN};
+#[cfg(feature = "mmap")]
+use crate::read_at::MappedFile;
+use crate::read_at::{ReadAt, SharedFile};
+use crate::Result;
+
+impl<R: BufRead + Seek + Sync> Database<R> {
+    /// Decodes every record on the rayon thread pool.
+    ///
+    /// The compressed data is read up front on the calling thread, and only decompression and decoding run in
+    /// parallel. Errors are reported per record.
+    pub fn par_records(&mut self) -> Result<impl ParallelIterator<Item = Result<Record>> + '_> {
+        let mut raw_records = self.iter_records()?.collect::<Vec<_>>();
+        // Read in file order so the reads stay sequential
+        raw_records.sort_by_key(|raw| raw.as_ref().map(|raw| raw.offset).ok());
+        let compressed = raw_records
+            .into_iter()
+            .map(|raw| {
+                let raw = raw?;
+                let mut compressed = vec![0u8; raw.compressed_len as usize];
+                self.file.seek(SeekFrom::Start(raw.offset as u64 + HEADER_LEN))?;
+                self.file.read_exact(&mut compressed)?;
+                Ok((raw, compressed))
+            })
+            .collect::<Vec<Result<(RawRecord, Vec<u8>)>>>();
+        let database = &*self;
+        Ok(compressed
+            .into_par_iter()
+            .map(move |result| result.and_then(|(raw, compressed)| database.decode(raw, &compressed[..]))))
+    }
+}
+
+impl<S: ReadAt + Sync> Database<S> {
+    fn par_records_at(&self) -> impl ParallelIterator<Item = Result<Record>> + '_ {
+        self.index.par_iter().map(|(_, raw)| self.resolve_at(raw.clone()))
+    }
+}
+
+impl Database<SharedFile> {
+    /// Decodes every record on the rayon thread pool. Errors are reported per record.
+    pub fn par_records(&self) -> impl ParallelIterator<Item = Result<Record>> + '_ {
+        self.par_records_at()
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Database<MappedFile> {
+    /// Decodes every record on the rayon thread pool. Errors are reported per record.
+    pub fn par_records(&self) -> impl ParallelIterator<Item = Result<Record>> + '_ {
+        self.par_records_at()
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::{DatabaseValue, Record, ARZ_MAGIC, HEADER_LEN, SUPPORTED_VERSIONS};
+use crate::adler32::{adler32, Adler32};
+use crate::error::check_limit;
+use crate::filetime::to_filetime;
+use crate::{Error, Result};
+
+#[derive(Default)]
+pub struct DatabaseWriter {
+    records: Vec<Record>,
+    index: HashMap<String, usize>,
+    // Written for every record, so the same records always produce the same file
+    timestamp: Option<SystemTime>,
+}
+
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    index: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&i) = self.index.get(s) {
+            return i;
+        }
+        let i = self.strings.len() as u32;
+        self.strings.push(s.to_string());
+        self.index.insert(s.to_string(), i);
+        i
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.strings.len() as u32).to_le_bytes());
+        for s in self.strings.iter() {
+            out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+        }
+    }
+}
+
+impl DatabaseWriter {
+    /// Creates a writer that stamps records with the Unix epoch, so writing the same records gives the same bytes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a writer that stamps every record with `timestamp`.
+    pub fn with_timestamp(timestamp: SystemTime) -> Self {
+        Self {
+            timestamp: Some(timestamp),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a record, replacing any earlier record with the same id.
+    pub fn add(&mut self, record: Record) {
+        match self.index.get(&record.id) {
+            Some(&i) => self.records[i] = record,
+            None => {
+                self.index.insert(record.id.clone(), self.records.len());
+                self.records.push(record);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn write<W: Write>(&self, mut out: W) -> Result<()> {
+        let timestamp = to_filetime(self.timestamp.unwrap_or(SystemTime::UNIX_EPOCH));
+        let mut strings = StringTable::default();
+        let mut data = vec![];
+        let mut table = vec![];
+        for record in self.records.iter() {
+            let uncompressed = encode_record(record, &mut strings)?;
+            let compressed = lz4::block::compress(&uncompressed[..], None, false)?;
+            let id = strings.intern(&record.id);
+            table.extend_from_slice(&id.to_le_bytes());
+            table.extend_from_slice(&check_limit(Some(&record.id), "kind length", record.kind.len())?.to_le_bytes());
+            table.extend_from_slice(record.kind.as_bytes());
+            table.extend_from_slice(&check_limit(Some(&record.id), "record offset", data.len())?.to_le_bytes());
+            table.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            table.extend_from_slice(&check_limit(Some(&record.id), "record length", uncompressed.len())?.to_le_bytes());
+            table.extend_from_slice(&timestamp.to_le_bytes());
+            data.extend_from_slice(&compressed[..]);
+        }
+        let mut string_table = vec![];
+        strings.write(&mut string_table);
+
+        let records_offset = check_limit(None, "file length", HEADER_LEN as usize + data.len())?;
+        let string_table_offset = check_limit(None, "file length", records_offset as usize + table.len())?;
+        check_limit(None, "file length", string_table_offset as usize + string_table.len())?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(&ARZ_MAGIC.to_le_bytes());
+        header.extend_from_slice(&SUPPORTED_VERSIONS[SUPPORTED_VERSIONS.len() - 1].to_le_bytes());
+        header.extend_from_slice(&records_offset.to_le_bytes());
+        header.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(self.records.len() as u32).to_le_bytes());
+        header.extend_from_slice(&string_table_offset.to_le_bytes());
+        header.extend_from_slice(&(string_table.len() as u32).to_le_bytes());
+
+        let mut file_checksum = Adler32::default();
+        for section in [&header, &data, &table, &string_table] {
+            file_checksum.update(&section[..]);
+            out.write_all(&section[..])?;
+        }
+        for checksum in [
+            file_checksum.finish(),
+            adler32(&table[..]),
+            adler32(&data[..]),
+            adler32(&string_table[..]),
+        ] {
+            out.write_all(&checksum.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+fn encode_record(record: &Record, strings: &mut StringTable) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    // Sort the fields so that the same records always produce the same bytes
+    let mut fields = record.data.iter().collect::<Vec<_>>();
+    fields.sort_by_key(|(key, _)| *key);
+    for (key, value) in fields {
+        let (kind, values): (u16, Vec<u32>) = match value {
+            DatabaseValue::Int(i) => (0, vec![*i]),
+            DatabaseValue::Ints(is) => (0, is.clone()),
+            DatabaseValue::Float(n) => (1, vec![n.to_bits()]),
+            DatabaseValue::Floats(ns) => (1, ns.iter().map(|n| n.to_bits()).collect()),
+            DatabaseValue::String(s) => (2, vec![strings.intern(s)]),
+            DatabaseValue::Strings(ss) => (2, ss.iter().map(|s| strings.intern(s)).collect()),
+            DatabaseValue::Bool(b) => (3, vec![*b as u32]),
+            DatabaseValue::Bools(bs) => (3, bs.iter().map(|b| *b as u32).collect()),
+        };
+        let count = u16::try_from(values.len()).map_err(|_| Error::LimitExceeded {
+            record: Some(record.id.clone()),
+            what: "value count",
+            len: values.len() as u64,
+        })?;
+        buf.extend_from_slice(&kind.to_le_bytes());
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&strings.intern(key).to_le_bytes());
+        for value in values {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arz::Database;
+
+    fn record(id: &str, data: Vec<(&str, DatabaseValue)>) -> Record {
+        Record {
+            id: id.to_string(),
+            kind: "ItemArtifact".to_string(),
+            data: data.into_iter().map(|(key, value)| (key.to_string(), value)).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let records = [
+            record(
+                "records/items/a.dbr",
+                vec![
+                    ("int", DatabaseValue::Int(7)),
+                    ("float", DatabaseValue::Float(1.5)),
+                    ("string", DatabaseValue::String("text".to_string())),
+                    ("bool", DatabaseValue::Bool(true)),
+                    ("ints", DatabaseValue::Ints(vec![1, 2, 3])),
+                    ("floats", DatabaseValue::Floats(vec![0.25, -2.0])),
+                    (
+                        "strings",
+                        DatabaseValue::Strings(vec!["x".to_string(), "text".to_string()]),
+                    ),
+                    ("bools", DatabaseValue::Bools(vec![false, true])),
+                ],
+            ),
+            record("records/items/b.dbr", vec![]),
+        ];
+        let mut writer = DatabaseWriter::new();
+        for record in records.iter() {
+            writer.add(record.clone());
+        }
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let mut db = Database::parse(&bytes[..]).unwrap();
+        db.verify().unwrap();
+        assert_eq!(db.len(), records.len());
+        for expected in records.iter() {
+            let actual = db.get(&expected.id).unwrap();
+            assert_eq!(actual.kind, expected.kind);
+            assert_eq!(actual.data, expected.data);
+        }
+    }
+
+    #[test]
+    fn writes_are_reproducible() {
+        let write = |writer: &DatabaseWriter| {
+            let mut bytes = vec![];
+            writer.write(&mut bytes).unwrap();
+            bytes
+        };
+        let record = record("records/a.dbr", vec![("value", DatabaseValue::Int(1))]);
+        let mut writer = DatabaseWriter::new();
+        writer.add(record.clone());
+        let first = write(&writer);
+        assert_eq!(write(&writer), first);
+
+        let timestamp = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        let mut writer = DatabaseWriter::with_timestamp(timestamp);
+        writer.add(record);
+        let stamped = write(&writer);
+        assert_ne!(stamped, first);
+        assert_eq!(write(&writer), stamped);
+    }
+
+    #[test]
+    fn add_replaces_record_with_same_id() {
+        let mut writer = DatabaseWriter::new();
+        writer.add(record("records/a.dbr", vec![("value", DatabaseValue::Int(1))]));
+        writer.add(record("records/a.dbr", vec![("value", DatabaseValue::Int(2))]));
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let mut db = Database::parse(&bytes[..]).unwrap();
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.get("records/a.dbr").unwrap().data["value"], DatabaseValue::Int(2));
+    }
+}
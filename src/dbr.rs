@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io::{BufRead, Seek};
+use std::path::Path;
+
+use crate::arz::{Database, DatabaseValue, Record};
+use crate::path::relative_path;
+use crate::{Error, Result};
+
+const CLASS: &str = "Class";
+
+/// Parses the `key,value,` lines of a `.dbr` file into a record.
+///
+/// The text format carries no types, so each value is read as an int, a float or a string, in that order of
+/// preference. Booleans come back as ints, and an empty value, as written for empty arrays of any type, comes back as
+/// an empty [`DatabaseValue::Strings`].
+pub fn parse(id: &str, text: &str) -> Result<Record> {
+    let mut data = HashMap::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once(',').ok_or_else(|| Error::DbrSyntax {
+            record: id.to_string(),
+            line: i + 1,
+        })?;
+        let value = value.strip_suffix(',').unwrap_or(value);
+        data.insert(key.to_string(), parse_value(value));
+    }
+    let kind = match data.get(CLASS) {
+        Some(DatabaseValue::String(class)) => class.clone(),
+        _ => String::new(),
+    };
+    Ok(Record {
+        id: id.to_string(),
+        kind,
+        data,
+    })
+}
+
+fn parse_value(value: &str) -> DatabaseValue {
+    if value.is_empty() {
+        return DatabaseValue::Strings(vec![]);
+    }
+    let parts = value.split(';').collect::<Vec<_>>();
+    if let Some(ints) = parts.iter().map(|part| parse_int(part)).collect::<Option<Vec<_>>>() {
+        ints.into()
+    } else if let Some(floats) = parts.iter().map(|part| parse_float(part)).collect::<Option<Vec<_>>>() {
+        floats.into()
+    } else {
+        parts
+            .into_iter()
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+fn parse_int(s: &str) -> Option<u32> {
+    // Ints are stored as u32 but written signed
+    s.parse::<i32>().map(|i| i as u32).or_else(|_| s.parse::<u32>()).ok()
+}
+
+fn parse_float(s: &str) -> Option<f32> {
+    // Keep words such as "inf" and "nan" as strings
+    if !s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.') {
+        return None;
+    }
+    s.parse::<f32>().ok()
+}
+
+fn write_values<T, F: Fn(&mut String, &T)>(out: &mut String, values: &[T], write: F) {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(';');
+        }
+        write(out, value);
+    }
+}
+
+fn write_int(out: &mut String, i: &u32) {
+    let _ = write!(out, "{}", *i as i32);
+}
+
+fn write_float(out: &mut String, n: &f32) {
+    // Debug formatting keeps a decimal point, so the value reads back as a float
+    let _ = write!(out, "{n:?}");
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push_str(s);
+}
+
+fn write_bool(out: &mut String, b: &bool) {
+    out.push(if *b { '1' } else { '0' });
+}
+
+impl Record {
+    pub fn to_dbr(&self) -> String {
+        let mut out = String::new();
+        let mut fields = self.data.iter().collect::<Vec<_>>();
+        fields.sort_by_key(|(key, _)| *key);
+        for (key, value) in fields {
+            out.push_str(key);
+            out.push(',');
+            match value {
+                DatabaseValue::Int(i) => write_int(&mut out, i),
+                DatabaseValue::Float(n) => write_float(&mut out, n),
+                DatabaseValue::String(s) => write_string(&mut out, s),
+                DatabaseValue::Bool(b) => write_bool(&mut out, b),
+                DatabaseValue::Ints(is) => write_values(&mut out, is, write_int),
+                DatabaseValue::Floats(ns) => write_values(&mut out, ns, write_float),
+                DatabaseValue::Strings(ss) => write_values(&mut out, ss, |out, s| write_string(out, s)),
+                DatabaseValue::Bools(bs) => write_values(&mut out, bs, write_bool),
+            }
+            out.push_str(",\n");
+        }
+        out
+    }
+}
+
+impl<R: BufRead + Seek> Database<R> {
+    /// Writes every record to `dir` as a `.dbr` file, following the `records/...` hierarchy of the record ids.
+    pub fn export_dbr_tree<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        let raw_records = self.iter_records()?.collect::<Result<Vec<_>>>()?;
+        for raw in raw_records.into_iter() {
+            let record = self.resolve(raw)?;
+            let path = relative_path(&record.id).ok_or_else(|| Error::UnsafePath {
+                name: record.id.clone(),
+            })?;
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, record.to_dbr())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let values = [
+            (
+                "int",
+                DatabaseValue::Int(-3i32 as u32),
+                DatabaseValue::Int(-3i32 as u32),
+            ),
+            ("float", DatabaseValue::Float(2.0), DatabaseValue::Float(2.0)),
+            (
+                "string",
+                DatabaseValue::String("text".into()),
+                DatabaseValue::String("text".into()),
+            ),
+            ("bool", DatabaseValue::Bool(true), DatabaseValue::Int(1)),
+            ("ints", DatabaseValue::Ints(vec![1, 2]), DatabaseValue::Ints(vec![1, 2])),
+            (
+                "floats",
+                DatabaseValue::Floats(vec![0.5, -1.0]),
+                DatabaseValue::Floats(vec![0.5, -1.0]),
+            ),
+            (
+                "strings",
+                DatabaseValue::Strings(vec!["a".into(), "b".into()]),
+                DatabaseValue::Strings(vec!["a".into(), "b".into()]),
+            ),
+            (
+                "bools",
+                DatabaseValue::Bools(vec![false, true]),
+                DatabaseValue::Ints(vec![0, 1]),
+            ),
+            ("empty", DatabaseValue::Ints(vec![]), DatabaseValue::Strings(vec![])),
+        ];
+        let record = Record {
+            id: "records/a.dbr".into(),
+            kind: "ItemArtifact".into(),
+            data: values
+                .iter()
+                .map(|(key, value, _)| (key.to_string(), value.clone()))
+                .chain([(CLASS.to_string(), DatabaseValue::String("ItemArtifact".into()))])
+                .collect(),
+        };
+        let parsed = parse(&record.id, &record.to_dbr()).unwrap();
+        assert_eq!(parsed.kind, record.kind);
+        for (key, _, expected) in values.iter() {
+            assert_eq!(parsed.data[*key], *expected, "{key}");
+        }
+    }
+
+    #[test]
+    fn parse_empty_value_as_empty_array() {
+        let record = parse("records/a.dbr", "Class,Item,\r\nempty,,\r\n").unwrap();
+        assert_eq!(record.kind, "Item");
+        assert_eq!(record.data["empty"], DatabaseValue::Strings(vec![]));
+    }
+}
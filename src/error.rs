@@ -0,0 +1,246 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::arz::ChecksumRegion;
+
+// LZ4 cannot expand a block by more than this factor
+const MAX_LZ4_RATIO: u64 = 255;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors produced while reading `.arz` databases and `.arc` archives.
+///
+/// Offsets are absolute file offsets, except for the value errors raised while decoding a record's fields, which
+/// carry the offset within that record's uncompressed data.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    BadMagic {
+        offset: u64,
+        found: u32,
+    },
+    UnsupportedVersion {
+        offset: u64,
+        version: u32,
+    },
+    CorruptStringIndex {
+        record: Option<String>,
+        offset: u64,
+        index: u32,
+    },
+    BadValueKind {
+        record: String,
+        offset: u64,
+        kind: u16,
+    },
+    BadBool {
+        record: String,
+        offset: u64,
+        value: u32,
+    },
+    Lz4 {
+        record: String,
+        offset: u64,
+        source: io::Error,
+    },
+    NonUtf8Name {
+        record: Option<String>,
+        offset: u64,
+    },
+    WrongFieldType {
+        record: String,
+        field: String,
+        expected: &'static str,
+    },
+    NotFound {
+        record: String,
+    },
+    OutOfBounds {
+        record: Option<String>,
+        offset: u64,
+        len: u64,
+        file_len: u64,
+    },
+    ImplausibleLength {
+        record: Option<String>,
+        offset: u64,
+        len: u64,
+    },
+    ChecksumMismatch {
+        region: ChecksumRegion,
+        offset: u64,
+        expected: u32,
+        actual: u32,
+    },
+    LimitExceeded {
+        record: Option<String>,
+        what: &'static str,
+        len: u64,
+    },
+    DbrSyntax {
+        record: String,
+        line: usize,
+    },
+    UnsafePath {
+        name: String,
+    },
+    TagSyntax {
+        file: String,
+        line: usize,
+    },
+    MissingFile {
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadMagic { offset, found } => write!(f, "Unexpected magic number {found} at offset {offset}"),
+            Self::UnsupportedVersion { offset, version } => {
+                write!(f, "Unsupported version {version} at offset {offset}")
+            }
+            Self::CorruptStringIndex { record, offset, index } => {
+                write!(f, "Failed to resolve string id {index} at offset {offset}")?;
+                write_record(f, record.as_deref())
+            }
+            Self::BadValueKind { record, offset, kind } => {
+                write!(f, "Unexpected database value kind {kind} at offset {offset}")?;
+                write_record(f, Some(record))
+            }
+            Self::BadBool { record, offset, value } => {
+                write!(f, "Unexpected boolean value {value} at offset {offset}")?;
+                write_record(f, Some(record))
+            }
+            Self::Lz4 { record, offset, source } => {
+                write!(f, "Failed to decompress data at offset {offset}: {source}")?;
+                write_record(f, Some(record))
+            }
+            Self::NonUtf8Name { record, offset } => {
+                write!(f, "Found non-utf8 bytes in name at offset {offset}")?;
+                write_record(f, record.as_deref())
+            }
+            Self::WrongFieldType {
+                record,
+                field,
+                expected,
+            } => {
+                write!(f, "Expected field {field} to be {expected}")?;
+                write_record(f, Some(record))
+            }
+            Self::NotFound { record } => write!(f, "Failed to get {record}"),
+            Self::OutOfBounds {
+                record,
+                offset,
+                len,
+                file_len,
+            } => {
+                write!(f, "{len} bytes at offset {offset} exceed the file length {file_len}")?;
+                write_record(f, record.as_deref())
+            }
+            Self::ImplausibleLength { record, offset, len } => {
+                write!(f, "Implausible uncompressed length {len} for data at offset {offset}")?;
+                write_record(f, record.as_deref())
+            }
+            Self::ChecksumMismatch {
+                region,
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Checksum mismatch in {region} at offset {offset}: expected {expected:#010x}, found {actual:#010x}"
+            ),
+            Self::LimitExceeded { record, what, len } => {
+                write!(f, "The {what} {len} exceeds the format limit")?;
+                write_record(f, record.as_deref())
+            }
+            Self::DbrSyntax { record, line } => write!(f, "Expected key,value, on line {line} of {record}"),
+            Self::UnsafePath { name } => write!(f, "Refusing to write {name} outside the destination"),
+            Self::TagSyntax { file, line } => write!(f, "Expected tag=text on line {line} of {file}"),
+            Self::MissingFile { path } => write!(f, "Could not find {}", path.display()),
+        }
+    }
+}
+
+fn write_record(f: &mut fmt::Formatter<'_>, record: Option<&str>) -> fmt::Result {
+    match record {
+        Some(record) => write!(f, " in {record}"),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn check_bounds(record: Option<&str>, offset: u64, len: u64, file_len: u64) -> Result<()> {
+    match offset.checked_add(len) {
+        Some(end) if end <= file_len => Ok(()),
+        _ => Err(Error::OutOfBounds {
+            record: record.map(|r| r.to_string()),
+            offset,
+            len,
+            file_len,
+        }),
+    }
+}
+
+pub(crate) fn check_plausible(
+    record: Option<&str>,
+    offset: u64,
+    compressed_len: u32,
+    uncompressed_len: u32,
+) -> Result<()> {
+    if uncompressed_len as u64 > compressed_len as u64 * MAX_LZ4_RATIO {
+        return Err(Error::ImplausibleLength {
+            record: record.map(|r| r.to_string()),
+            offset,
+            len: uncompressed_len as u64,
+        });
+    }
+    Ok(())
+}
+
+// Data that decodes to another length than its header promised is corrupt, and would otherwise leave zeroes behind
+pub(crate) fn check_length(record: Option<&str>, offset: u64, expected: u64, len: u64) -> Result<()> {
+    if len != expected {
+        return Err(Error::ImplausibleLength {
+            record: record.map(|r| r.to_string()),
+            offset,
+            len,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_limit(record: Option<&str>, what: &'static str, len: usize) -> Result<u32> {
+    u32::try_from(len).map_err(|_| Error::LimitExceeded {
+        record: record.map(|r| r.to_string()),
+        what,
+        len: len as u64,
+    })
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Lz4 { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            other => io::Error::other(other),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// FILETIME counts 100ns intervals since 1601-01-01
+const UNIX_EPOCH_INTERVALS: u64 = 116_444_736_000_000_000;
+
+fn intervals(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos() / 100).unwrap_or(u64::MAX)
+}
+
+pub fn to_filetime(time: SystemTime) -> u64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => UNIX_EPOCH_INTERVALS.saturating_add(intervals(since)),
+        Err(err) => UNIX_EPOCH_INTERVALS.saturating_sub(intervals(err.duration())),
+    }
+}
+
+pub fn from_filetime(filetime: u64) -> SystemTime {
+    let duration = |intervals: u64| Duration::new(intervals / 10_000_000, (intervals % 10_000_000) as u32 * 100);
+    if filetime >= UNIX_EPOCH_INTERVALS {
+        UNIX_EPOCH + duration(filetime - UNIX_EPOCH_INTERVALS)
+    } else {
+        UNIX_EPOCH - duration(UNIX_EPOCH_INTERVALS - filetime)
+    }
+}
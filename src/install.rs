@@ -0,0 +1,174 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::arc::{Archive, ResourceFs};
+use crate::arz::{Database, LayeredDatabase};
+use crate::localization::Localization;
+use crate::path::{files_with_extension, find_ignore_case, find_path_ignore_case};
+use crate::{Error, Result, SharedFile};
+
+const ENGLISH: &str = "en";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Expansion {
+    AshesOfMalmouth,
+    ForgottenGods,
+    FangsOfAsterkarn,
+}
+
+impl Expansion {
+    /// Every expansion, in the order their databases are layered.
+    pub const ALL: [Expansion; 3] = [Self::AshesOfMalmouth, Self::ForgottenGods, Self::FangsOfAsterkarn];
+
+    /// The expansion's folder below the install root.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Self::AshesOfMalmouth => "gdx1",
+            Self::ForgottenGods => "gdx2",
+            Self::FangsOfAsterkarn => "gdx3",
+        }
+    }
+}
+
+impl fmt::Display for Expansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AshesOfMalmouth => write!(f, "Ashes of Malmouth"),
+            Self::ForgottenGods => write!(f, "Forgotten Gods"),
+            Self::FangsOfAsterkarn => write!(f, "Fangs of Asterkarn"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// Language code of the `text_<language>.arc` archives to load, such as `en` or `zh`. A community translation
+    /// named after the language in the install's `localization` folder, either a folder or, with the `zip`
+    /// feature, a `.zip` of tags files, is loaded on top of them. English is always loaded too, as the language's
+    /// fallback.
+    pub language: String,
+    /// Mod folder layered on top of the game, relative to the install root unless absolute.
+    pub mod_dir: Option<PathBuf>,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            language: ENGLISH.to_string(),
+            mod_dir: None,
+        }
+    }
+}
+
+/// The databases, archives and text of a Grim Dawn installation, with expansions and an optional mod layered over
+/// the base game.
+pub struct GameInstall {
+    pub root: PathBuf,
+    pub expansions: Vec<Expansion>,
+    pub database: LayeredDatabase<SharedFile>,
+    /// Every `resources/*.arc` archive, mounted under its lowercase file stem so `Items.arc` provides `items/...`.
+    pub resources: ResourceFs<SharedFile>,
+    /// Tags from the text archives of the chosen language and English, where later layers override earlier ones.
+    /// The chosen language is the current one, so affixes can be localized with it directly.
+    pub localization: Localization,
+}
+
+impl GameInstall {
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self> {
+        Self::open_with_options(root, &InstallOptions::default())
+    }
+
+    /// Opens the install at `root`, matching file and folder names without regard to case.
+    pub fn open_with_options<P: AsRef<Path>>(root: P, options: &InstallOptions) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        let base_database =
+            find_path_ignore_case(&root, &["database", "database.arz"]).ok_or_else(|| Error::MissingFile {
+                path: root.join("database").join("database.arz"),
+            })?;
+
+        let mut install = Self {
+            root: root.clone(),
+            expansions: vec![],
+            database: LayeredDatabase::new(),
+            resources: ResourceFs::new(),
+            localization: Localization::new(),
+        };
+        let language = options.language.to_ascii_lowercase();
+        if language != ENGLISH {
+            install.localization.set_fallback(&language, ENGLISH);
+        }
+        install.localization.set_current_language(&language);
+        install.database.push("base", Database::open_shared(base_database)?);
+        install.load_resources(&root, options)?;
+
+        for expansion in Expansion::ALL {
+            let Some(dir) = find_ignore_case(&root, expansion.dir_name()) else {
+                continue;
+            };
+            install.load_layer(expansion.dir_name(), &dir, options)?;
+            install.expansions.push(expansion);
+        }
+        if let Some(mod_dir) = &options.mod_dir {
+            let dir = root.join(mod_dir);
+            if !dir.is_dir() {
+                return Err(Error::MissingFile { path: dir });
+            }
+            let name = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "mod".to_string());
+            install.load_layer(&name, &dir, options)?;
+        }
+        install.load_localization_packs(&root, &language)?;
+        if language != ENGLISH && install.localization.language(&language).is_none() {
+            return Err(Error::MissingFile {
+                path: root.join("localization").join(&language),
+            });
+        }
+        Ok(install)
+    }
+
+    fn load_layer(&mut self, name: &str, dir: &Path, options: &InstallOptions) -> Result<()> {
+        if let Some(database_dir) = find_ignore_case(dir, "database") {
+            for path in files_with_extension(&database_dir, "arz")? {
+                self.database.push(name, Database::open_shared(path)?);
+            }
+        }
+        self.load_resources(dir, options)
+    }
+
+    fn load_resources(&mut self, dir: &Path, options: &InstallOptions) -> Result<()> {
+        let Some(resources) = find_ignore_case(dir, "resources") else {
+            return Ok(());
+        };
+        for path in files_with_extension(&resources, "arc")? {
+            let archive = Archive::open_shared(&path)?;
+            let prefix = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            if let Some(language) = prefix.strip_prefix("text_") {
+                if language.eq_ignore_ascii_case(&options.language) || language == ENGLISH {
+                    self.localization.language_mut(language).add_shared_archive(&archive)?;
+                }
+            }
+            self.resources.mount(&prefix, archive);
+        }
+        Ok(())
+    }
+
+    // Community translations live in the root `localization` folder as `<language>/` or `<language>.zip`
+    fn load_localization_packs(&mut self, root: &Path, language: &str) -> Result<()> {
+        let Some(dir) = find_ignore_case(root, "localization") else {
+            return Ok(());
+        };
+        if let Some(pack) = find_ignore_case(&dir, language).filter(|path| path.is_dir()) {
+            self.localization.add_pack_dir(language, pack)?;
+        }
+        #[cfg(feature = "zip")]
+        if let Some(pack) = find_ignore_case(&dir, &format!("{language}.zip")).filter(|path| path.is_file()) {
+            self.localization.add_pack_zip(language, pack)?;
+        }
+        Ok(())
+    }
+}
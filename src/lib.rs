@@ -1,6 +1,19 @@
+mod adler32;
 pub mod affix;
 pub mod affix_table;
 pub mod arc;
 pub mod arz;
 mod buf_read_ext;
+pub mod dbr;
+mod error;
+mod filetime;
+pub mod install;
+pub mod localization;
+mod path;
+mod read_at;
 pub mod tags;
+
+pub use error::{Error, Result};
+#[cfg(feature = "mmap")]
+pub use read_at::MappedFile;
+pub use read_at::SharedFile;
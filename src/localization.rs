@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fs;
+#[cfg(feature = "zip")]
+use std::fs::File;
+use std::io::{self, BufRead, Seek};
+#[cfg(feature = "zip")]
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::arc::Archive;
+use crate::tags::{compare, decode, parse_lines, CoverageReport, Encoding};
+use crate::{Error, Result, SharedFile};
+
+/// A tag defined more than once. The later definition is the one kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateTag {
+    pub tag: String,
+    pub first_file: String,
+    pub first_line: usize,
+    pub file: String,
+    pub line: usize,
+}
+
+/// Anything tags can be looked up in, such as a plain map, a [`TagSet`] or a [`LanguageView`] with fallbacks.
+pub trait TagLookup {
+    fn lookup(&self, tag: &str) -> Option<&str>;
+}
+
+impl TagLookup for HashMap<String, String> {
+    fn lookup(&self, tag: &str) -> Option<&str> {
+        self.get(tag).map(|text| text.as_str())
+    }
+}
+
+/// The tags of one language, merged from several tags files.
+#[derive(Debug, Clone, Default)]
+pub struct TagSet {
+    tags: HashMap<String, String>,
+    // File and line each tag was last defined on
+    sources: HashMap<String, (String, usize)>,
+    duplicates: Vec<DuplicateTag>,
+    encodings: HashMap<String, Encoding>,
+}
+
+impl TagSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses and merges every `tags*.txt` entry of a `text_*.arc` archive, in the order they are stored.
+    pub fn from_archive<R: BufRead + Seek>(archive: &mut Archive<R>) -> Result<Self> {
+        let mut tags = Self::new();
+        tags.add_archive(archive)?;
+        Ok(tags)
+    }
+
+    /// Merges every `tags*.txt` entry of `archive` into the existing tags.
+    pub fn add_archive<R: BufRead + Seek>(&mut self, archive: &mut Archive<R>) -> Result<()> {
+        for entry in archive.entries() {
+            if is_tags_file(&entry.name) {
+                let record = archive.get(&entry.name)?;
+                self.add_file(&entry.name, &record.data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`TagSet::add_archive`], for an archive opened with [`Archive::open_shared`].
+    pub fn add_shared_archive(&mut self, archive: &Archive<SharedFile>) -> Result<()> {
+        for entry in archive.entries() {
+            if is_tags_file(&entry.name) {
+                let record = archive.get(&entry.name)?;
+                self.add_file(&entry.name, &record.data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges every `tags*.txt` file below `dir`, such as an unpacked community translation.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        for path in files_below(dir)? {
+            let name = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            if is_tags_file(&name) {
+                self.add_file(&name, &fs::read(&path)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges every `tags*.txt` file in the zip at `path`, such as a packed community translation.
+    #[cfg(feature = "zip")]
+    pub fn add_zip<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(path)?)).map_err(io::Error::from)?;
+        let mut names = zip
+            .file_names()
+            .filter(|name| is_tags_file(name))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        for name in names {
+            let mut bytes = vec![];
+            zip.by_name(&name).map_err(io::Error::from)?.read_to_end(&mut bytes)?;
+            self.add_file(&name, &bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Parses the tags file `bytes` and merges it into the existing tags, overriding any already defined.
+    ///
+    /// The file's encoding is detected with [`Encoding::detect`] and can be read back with
+    /// [`TagSet::encoding`].
+    pub fn add_file(&mut self, file: &str, bytes: &[u8]) -> Result<()> {
+        let (text, encoding) = decode(bytes);
+        self.encodings.insert(file.to_string(), encoding);
+        for entry in parse_lines(&text) {
+            let (line, tag, text) = entry.map_err(|err| Error::TagSyntax {
+                file: file.to_string(),
+                line: err.0,
+            })?;
+            let source = (file.to_string(), line);
+            if let Some((first_file, first_line)) = self.sources.insert(tag.clone(), source) {
+                self.duplicates.push(DuplicateTag {
+                    tag: tag.clone(),
+                    first_file,
+                    first_line,
+                    file: file.to_string(),
+                    line,
+                });
+            }
+            self.tags.insert(tag, text);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        self.tags.get(tag).map(|text| text.as_str())
+    }
+
+    /// Returns the file and line `tag` was defined on.
+    pub fn source(&self, tag: &str) -> Option<(&str, usize)> {
+        self.sources.get(tag).map(|(file, line)| (file.as_str(), *line))
+    }
+
+    /// Returns the encoding detected for `file` when it was added.
+    pub fn encoding(&self, file: &str) -> Option<Encoding> {
+        self.encodings.get(file).copied()
+    }
+
+    /// Returns every tag defined more than once, in the order the repeated definitions were read.
+    pub fn duplicates(&self) -> &[DuplicateTag] {
+        &self.duplicates
+    }
+
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+impl TagLookup for TagSet {
+    fn lookup(&self, tag: &str) -> Option<&str> {
+        self.get(tag)
+    }
+}
+
+/// Tag sets for several languages, where each language can fall back to another for the tags it lacks.
+///
+/// As a [`TagLookup`], it looks tags up in its current language and that language's fallbacks.
+#[derive(Debug, Clone, Default)]
+pub struct Localization {
+    languages: HashMap<String, TagSet>,
+    fallbacks: HashMap<String, String>,
+    current: Option<String>,
+}
+
+impl Localization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the tags of a `text_xx.arc` archive as `language`, which becomes the current language.
+    pub fn from_archive<R: BufRead + Seek>(language: &str, archive: &mut Archive<R>) -> Result<Self> {
+        let mut localization = Self::new();
+        localization.add_archive(language, archive)?;
+        localization.set_current_language(language);
+        Ok(localization)
+    }
+
+    /// Returns the language tags are looked up in through [`TagLookup`].
+    pub fn current_language(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn set_current_language(&mut self, language: &str) {
+        self.current = Some(language.to_string());
+    }
+
+    /// Returns the file and line `tag` was defined on in the current language.
+    pub fn source(&self, tag: &str) -> Option<(&str, usize)> {
+        self.current_set()?.source(tag)
+    }
+
+    /// Returns the tags defined more than once in the current language.
+    pub fn duplicates(&self) -> &[DuplicateTag] {
+        self.current_set().map(|set| set.duplicates()).unwrap_or_default()
+    }
+
+    fn current_set(&self) -> Option<&TagSet> {
+        self.languages.get(self.current.as_deref()?)
+    }
+
+    /// Returns the tags of `language`, adding an empty set if it has none yet.
+    pub fn language_mut(&mut self, language: &str) -> &mut TagSet {
+        self.languages.entry(language.to_string()).or_default()
+    }
+
+    pub fn language(&self, language: &str) -> Option<&TagSet> {
+        self.languages.get(language)
+    }
+
+    /// Returns the loaded languages in sorted order.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages = self
+            .languages
+            .keys()
+            .map(|language| language.as_str())
+            .collect::<Vec<_>>();
+        languages.sort_unstable();
+        languages
+    }
+
+    /// Merges an official `text_xx.arc` archive into `language`.
+    pub fn add_archive<R: BufRead + Seek>(&mut self, language: &str, archive: &mut Archive<R>) -> Result<()> {
+        self.language_mut(language).add_archive(archive)
+    }
+
+    /// Merges a community translation pack, given as a directory of tags files, into `language`.
+    pub fn add_pack_dir<P: AsRef<Path>>(&mut self, language: &str, dir: P) -> Result<()> {
+        self.language_mut(language).add_dir(dir)
+    }
+
+    /// Merges a community translation pack, given as a zip of tags files, into `language`.
+    #[cfg(feature = "zip")]
+    pub fn add_pack_zip<P: AsRef<Path>>(&mut self, language: &str, path: P) -> Result<()> {
+        self.language_mut(language).add_zip(path)
+    }
+
+    /// Makes `language` fall back to `fallback` for the tags it lacks. Fallbacks chain, so with `zh` falling
+    /// back to `en`, a language falling back to `zh` ends up at `en` too.
+    pub fn set_fallback(&mut self, language: &str, fallback: &str) {
+        self.fallbacks.insert(language.to_string(), fallback.to_string());
+    }
+
+    /// Returns `language` followed by its fallbacks, in the order they are searched.
+    pub fn fallback_chain<'a>(&'a self, language: &'a str) -> Vec<&'a str> {
+        let mut chain = vec![language];
+        while let Some(next) = self.fallbacks.get(chain[chain.len() - 1]) {
+            if chain.contains(&next.as_str()) {
+                break;
+            }
+            chain.push(next);
+        }
+        chain
+    }
+
+    /// Looks tags up in `language`, then along its fallback chain.
+    pub fn view(&self, language: &str) -> LanguageView<'_> {
+        LanguageView {
+            sets: self
+                .fallback_chain(language)
+                .into_iter()
+                .filter_map(|language| self.languages.get(language))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, language: &str, tag: &str) -> Option<&str> {
+        self.fallback_chain(language)
+            .into_iter()
+            .filter_map(|language| self.languages.get(language))
+            .find_map(|set| set.get(tag))
+    }
+
+    /// Compares the tags of `target` against those of `source`, such as a translation against English. A language
+    /// that is not loaded counts as having no tags, and fallbacks are not consulted.
+    pub fn coverage(&self, source: &str, target: &str) -> CoverageReport {
+        let empty = HashMap::new();
+        let tags = |language| self.language(language).map(|set| set.tags()).unwrap_or(&empty);
+        compare(tags(source), tags(target))
+    }
+
+    /// Returns the text of `tag` in `language` or its fallbacks, or the tag itself when no language has it.
+    pub fn text<'a>(&'a self, language: &str, tag: &'a str) -> &'a str {
+        self.get(language, tag).unwrap_or(tag)
+    }
+}
+
+impl TagLookup for Localization {
+    fn lookup(&self, tag: &str) -> Option<&str> {
+        self.get(self.current.as_deref()?, tag)
+    }
+}
+
+/// One language of a [`Localization`] together with its fallbacks.
+pub struct LanguageView<'a> {
+    sets: Vec<&'a TagSet>,
+}
+
+impl TagLookup for LanguageView<'_> {
+    fn lookup(&self, tag: &str) -> Option<&str> {
+        self.sets.iter().find_map(|set| set.get(tag))
+    }
+}
+
+fn files_below(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub(crate) fn is_tags_file(name: &str) -> bool {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name).to_ascii_lowercase();
+    name.starts_with("tags") && name.ends_with(".txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::affix::Affix;
+    use crate::arc::ArchiveWriter;
+    use crate::arz::{DatabaseValue, Record};
+
+    #[test]
+    fn from_archive_localizes_affixes() {
+        let mut writer = ArchiveWriter::new();
+        writer.add(
+            "tags_items.txt",
+            b"tagRusty=Rostig\ntagSharp=Scharf\n".to_vec(),
+            SystemTime::UNIX_EPOCH,
+        );
+        writer.add(
+            "tags_skills.txt",
+            b"tagRusty=Rostiger\n".to_vec(),
+            SystemTime::UNIX_EPOCH,
+        );
+        writer.add("readme.txt", b"not=tags\n".to_vec(), SystemTime::UNIX_EPOCH);
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+
+        let localization = Localization::from_archive("de", &mut Archive::parse(&bytes[..]).unwrap()).unwrap();
+        assert_eq!(localization.languages(), ["de"]);
+        assert_eq!(localization.current_language(), Some("de"));
+        assert_eq!(localization.lookup("not"), None);
+        assert_eq!(localization.source("tagRusty"), Some(("tags_skills.txt", 1)));
+        assert_eq!(localization.duplicates().len(), 1);
+        assert_eq!(localization.duplicates()[0].first_file, "tags_items.txt");
+
+        let record = Record {
+            id: "records/items/lootaffixes/prefix/rusty.dbr".to_string(),
+            kind: "LootRandomizer".to_string(),
+            data: HashMap::from([(
+                "lootRandomizerName".to_string(),
+                DatabaseValue::String("tagRusty".to_string()),
+            )]),
+        };
+        let affix = Affix::try_from(record).unwrap();
+        assert_eq!(affix.localize(&localization), "Rostiger");
+    }
+}
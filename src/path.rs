@@ -0,0 +1,60 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Converts an archive or record name into a relative path, refusing names that would escape the destination
+pub fn relative_path(name: &str) -> Option<PathBuf> {
+    if name.starts_with(['/', '\\']) {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    for segment in name.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            // Drive letters and alternate data streams
+            _ if segment.contains(':') => return None,
+            _ => path.push(segment),
+        }
+    }
+    if path.as_os_str().is_empty() {
+        return None;
+    }
+    Some(path)
+}
+
+// Finds `name` in `dir`, ignoring ASCII case since installs copied from Windows keep their mixed-case names
+pub fn find_ignore_case(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_str().is_some_and(|s| s.eq_ignore_ascii_case(name)))
+        .map(|entry| entry.path())
+}
+
+// Follows `segments` below `dir`, matching each one without regard to ASCII case
+pub fn find_path_ignore_case(dir: &Path, segments: &[&str]) -> Option<PathBuf> {
+    segments
+        .iter()
+        .try_fold(dir.to_path_buf(), |path, segment| find_ignore_case(&path, segment))
+}
+
+// Lists the files in `dir` with the extension `ext`, in case-insensitive name order
+pub fn files_with_extension(dir: &Path, ext: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+        })
+        .collect::<Vec<_>>();
+    files.sort_by_key(|path| path.file_name().map(|name| name.to_string_lossy().to_lowercase()));
+    Ok(files)
+}
@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+
+// Positional reads through a shared reference, for sources that do not need a seek cursor
+pub trait ReadAt {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Cow<'_, [u8]>>;
+}
+
+#[cfg(feature = "mmap")]
+fn slice_at(bytes: &[u8], offset: u64, len: usize) -> io::Result<&[u8]> {
+    usize::try_from(offset)
+        .ok()
+        .and_then(|start| bytes.get(start..start.checked_add(len)?))
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+}
+
+/// A read-only memory map of a database or archive file.
+#[cfg(feature = "mmap")]
+#[derive(Clone)]
+pub struct MappedFile(Arc<memmap2::Mmap>);
+
+#[cfg(feature = "mmap")]
+impl MappedFile {
+    /// Maps the file at `path`.
+    ///
+    /// # Safety
+    ///
+    /// The map reflects later changes to the file, so the file must not be modified or truncated, by this or any
+    /// other process, while the map or anything read from it is alive. Doing so is undefined behavior.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: upheld by the caller
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self(Arc::new(map)))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl ReadAt for MappedFile {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Cow<'_, [u8]>> {
+        slice_at(self.as_ref(), offset, len).map(Cow::Borrowed)
+    }
+}
+
+/// A file read with positional reads, so any number of threads can read it at once without a shared cursor.
+pub struct SharedFile(File);
+
+impl SharedFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        File::open(path).map(Self)
+    }
+
+    // Independent handle for the sequential reads done while opening
+    pub(crate) fn try_clone(&self) -> io::Result<File> {
+        self.0.try_clone()
+    }
+}
+
+impl ReadAt for SharedFile {
+    #[cfg(unix)]
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Cow<'_, [u8]>> {
+        use std::os::unix::fs::FileExt;
+
+        let mut buf = vec![0u8; len];
+        self.0.read_exact_at(&mut buf, offset)?;
+        Ok(Cow::Owned(buf))
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Cow<'_, [u8]>> {
+        use std::os::windows::fs::FileExt;
+
+        let mut buf = vec![0u8; len];
+        let mut filled = 0;
+        while filled < len {
+            match self.0.seek_read(&mut buf[filled..], offset + filled as u64) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Cow::Owned(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::thread;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::arc::{Archive, ArchiveWriter, ResourceFs};
+    use crate::arz::{Database, DatabaseValue, DatabaseWriter, LayeredDatabase, Record};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn shared_sources_are_send_and_sync() {
+        assert_send_sync::<Database<SharedFile>>();
+        assert_send_sync::<Archive<SharedFile>>();
+        assert_send_sync::<LayeredDatabase<SharedFile>>();
+        assert_send_sync::<ResourceFs<SharedFile>>();
+        #[cfg(feature = "mmap")]
+        {
+            assert_send_sync::<Database<MappedFile>>();
+            assert_send_sync::<Archive<MappedFile>>();
+        }
+    }
+
+    #[test]
+    fn read_from_several_threads() {
+        let id = |i: u32| format!("records/items/item_{i:03}.dbr");
+        let mut database = DatabaseWriter::new();
+        let mut archive = ArchiveWriter::new();
+        for i in 0..64 {
+            database.add(Record {
+                id: id(i),
+                kind: "ItemArtifact".to_string(),
+                data: HashMap::from([("levelRequirement".to_string(), DatabaseValue::Int(i))]),
+            });
+            archive.add(id(i), vec![i as u8; 70_000], SystemTime::UNIX_EPOCH);
+        }
+        let dir = std::env::temp_dir();
+        let database_path = dir.join(format!("lib_gddb_threads_{}.arz", std::process::id()));
+        let archive_path = dir.join(format!("lib_gddb_threads_{}.arc", std::process::id()));
+        database.write_file(&database_path).unwrap();
+        archive.write_file(&archive_path).unwrap();
+
+        let database = Database::open_shared(&database_path).unwrap();
+        let archive = Archive::open_shared(&archive_path).unwrap();
+        thread::scope(|scope| {
+            for thread in 0..4 {
+                let (database, archive) = (&database, &archive);
+                scope.spawn(move || {
+                    // Each thread walks the records in a different order
+                    for i in (0..64).map(|i| (i * 5 + thread * 16) % 64) {
+                        let record = database.get(&id(i)).unwrap();
+                        assert_eq!(record.data["levelRequirement"], DatabaseValue::Int(i));
+                        assert_eq!(archive.get(&id(i)).unwrap().data, vec![i as u8; 70_000]);
+                    }
+                });
+            }
+        });
+        fs::remove_file(database_path).unwrap();
+        fs::remove_file(archive_path).unwrap();
+    }
+}
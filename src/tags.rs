@@ -1,35 +1,46 @@
 use std::collections::HashMap;
-use std::io::{BufRead, Cursor};
+
+mod coverage;
+mod document;
+mod encoding;
+mod text;
+mod variants;
+
+pub use coverage::{compare, CoverageReport, Mismatch};
+pub use document::Document;
+pub use encoding::{decode, Encoding};
+pub use text::{Placeholder, Segment, TagText};
+pub use variants::{select_variant, Form, Gender, Number, Variants};
 
 #[derive(Debug)]
 pub struct TagParseError(pub usize, pub String);
 
+/// Parses a tags file, detecting its encoding with [`Encoding::detect`].
 pub fn parse(bytes: &[u8]) -> Result<HashMap<String, String>, TagParseError> {
-    let mut result = HashMap::default();
-
-    let mut buf = Cursor::new(bytes);
+    parse_with_encoding(bytes).map(|(tags, _)| tags)
+}
 
-    let mut line = String::new();
-    let mut line_number = 0;
+/// Like [`parse`], also returning the encoding the file was decoded from.
+pub fn parse_with_encoding(bytes: &[u8]) -> Result<(HashMap<String, String>, Encoding), TagParseError> {
+    let (text, encoding) = decode(bytes);
+    let tags = parse_lines(&text)
+        .map(|entry| entry.map(|(_, tag, value)| (tag, value)))
+        .collect::<Result<_, _>>()?;
+    Ok((tags, encoding))
+}
 
-    while let Ok(bytes_read) = buf.read_line(&mut line) {
-        if bytes_read == 0 {
-            // EOF
-            break;
-        }
-        line_number += 1;
+// Yields the line number, tag and text of every tag line in file order, duplicates included
+pub(crate) fn parse_lines(text: &str) -> impl Iterator<Item = Result<(usize, String, String), TagParseError>> + '_ {
+    text.split_inclusive('\n').enumerate().filter_map(|(i, line)| {
         let content = line.trim();
-        if !content.is_empty() && !content.starts_with('#') {
-            let mut split = content.split('=');
-            let tag = split.next().ok_or_else(|| TagParseError(line_number, line.clone()))?;
-            let value = split.next().ok_or_else(|| TagParseError(line_number, line.clone()))?;
-            if split.next().is_some() {
-                return Err(TagParseError(line_number, line.clone()));
-            }
-            result.insert(tag.to_string(), value.to_string());
+        if content.is_empty() || content.starts_with('#') {
+            return None;
         }
-        line.clear();
-    }
-
-    Ok(result)
+        // Only the first '=' separates the tag, as values may contain more
+        let entry = match content.split_once('=') {
+            Some((tag, value)) => Ok((i + 1, tag.to_string(), value.to_string())),
+            None => Err(TagParseError(i + 1, line.to_string())),
+        };
+        Some(entry)
+    })
 }
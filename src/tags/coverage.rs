@@ -0,0 +1,170 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{Segment, TagText};
+
+/// A tag whose markup differs between the source and the translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub tag: String,
+    pub source: Vec<String>,
+    pub target: Vec<String>,
+}
+
+/// How completely and consistently a translation covers its source language. Every list is sorted by tag.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CoverageReport {
+    /// Number of tags in the source.
+    pub source_len: usize,
+    /// Tags of the source that the translation lacks.
+    pub missing: Vec<String>,
+    /// Tags of the translation that the source lacks.
+    pub extra: Vec<String>,
+    /// Tags whose translation is identical to the source text.
+    pub untranslated: Vec<String>,
+    /// Tags whose translation uses other `{%...}` placeholders than the source.
+    pub placeholder_mismatches: Vec<Mismatch>,
+    /// Tags whose translation uses other `{^X}` color codes than the source.
+    pub color_mismatches: Vec<Mismatch>,
+}
+
+impl CoverageReport {
+    /// Returns the share of source tags that are present and translated, from 0 to 1.
+    pub fn translated_ratio(&self) -> f64 {
+        if self.source_len == 0 {
+            return 1.0;
+        }
+        let translated = self
+            .source_len
+            .saturating_sub(self.missing.len() + self.untranslated.len());
+        translated as f64 / self.source_len as f64
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.untranslated.is_empty()
+            && self.placeholder_mismatches.is_empty()
+            && self.color_mismatches.is_empty()
+    }
+}
+
+/// Compares a translation against its source, both as returned by [`parse`](super::parse).
+///
+/// Placeholders and colors are compared as sets, since translations may reorder them and gendered variants repeat
+/// them. Text without any letters, such as a lone placeholder, does not count as untranslated.
+pub fn compare(source: &HashMap<String, String>, target: &HashMap<String, String>) -> CoverageReport {
+    let mut report = CoverageReport {
+        source_len: source.len(),
+        ..Default::default()
+    };
+    for (tag, source_text) in source.iter() {
+        let Some(target_text) = target.get(tag) else {
+            report.missing.push(tag.clone());
+            continue;
+        };
+        let source_markup = Markup::of(source_text);
+        let target_markup = Markup::of(target_text);
+        if target_text == source_text && source_markup.has_letters {
+            report.untranslated.push(tag.clone());
+        }
+        if source_markup.placeholders != target_markup.placeholders {
+            report.placeholder_mismatches.push(Mismatch {
+                tag: tag.clone(),
+                source: source_markup.placeholders.into_iter().collect(),
+                target: target_markup.placeholders.into_iter().collect(),
+            });
+        }
+        if source_markup.colors != target_markup.colors {
+            report.color_mismatches.push(Mismatch {
+                tag: tag.clone(),
+                source: source_markup.colors.into_iter().collect(),
+                target: target_markup.colors.into_iter().collect(),
+            });
+        }
+    }
+    report.extra = target
+        .keys()
+        .filter(|tag| !source.contains_key(*tag))
+        .cloned()
+        .collect();
+
+    report.missing.sort_unstable();
+    report.extra.sort_unstable();
+    report.untranslated.sort_unstable();
+    report.placeholder_mismatches.sort_by(|a, b| a.tag.cmp(&b.tag));
+    report.color_mismatches.sort_by(|a, b| a.tag.cmp(&b.tag));
+    report
+}
+
+#[derive(Default)]
+struct Markup {
+    placeholders: BTreeSet<String>,
+    colors: BTreeSet<String>,
+    // Whether the text outside the markup has any letters to translate
+    has_letters: bool,
+}
+
+impl Markup {
+    fn of(text: &str) -> Self {
+        let mut markup = Self::default();
+        for segment in TagText::parse(text).segments {
+            match segment {
+                Segment::Placeholder(_) => {
+                    let source = TagText {
+                        segments: vec![segment],
+                    };
+                    markup.placeholders.insert(source.to_string());
+                }
+                Segment::Color(color) => {
+                    markup.colors.insert(format!("{{^{}}}", color.to_ascii_uppercase()));
+                }
+                Segment::Text(text) => markup.has_letters |= text.chars().any(|c| c.is_alphabetic()),
+                _ => {}
+            }
+        }
+        markup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(tag, text)| (tag.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn compare_translation() {
+        let source = tags(&[
+            ("tagA", "Sword"),
+            ("tagB", "{^R}Deals {%d0} damage"),
+            ("tagC", "Shield"),
+            ("tagD", "{%s0}"),
+        ]);
+        let target = tags(&[
+            ("tagA", "Schwert"),
+            ("tagB", "{^G}Verursacht {%f0} Schaden"),
+            ("tagD", "{%s0}"),
+            ("tagE", "Helm"),
+        ]);
+        let report = compare(&source, &target);
+        assert_eq!(report.source_len, 4);
+        assert_eq!(report.missing, ["tagC"]);
+        assert_eq!(report.extra, ["tagE"]);
+        assert!(report.untranslated.is_empty());
+        assert_eq!(report.placeholder_mismatches[0].tag, "tagB");
+        assert_eq!(report.color_mismatches[0].tag, "tagB");
+        assert_eq!(report.translated_ratio(), 0.75);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn translated_ratio_of_empty_source() {
+        let report = compare(&HashMap::new(), &tags(&[("tagA", "Schwert")]));
+        assert_eq!(report.translated_ratio(), 1.0);
+    }
+}
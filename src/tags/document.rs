@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{decode, Encoding, TagParseError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    // Blank lines and comments, kept as written
+    Other(String),
+    Entry {
+        tag: String,
+        value: String,
+        // The line as read, until the entry is changed
+        raw: Option<String>,
+        eol: String,
+    },
+}
+
+/// A tags file that keeps its comments, blank lines, order and line endings, so it can be edited and written
+/// back without losing anything.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document {
+    lines: Vec<Line>,
+    encoding: Encoding,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Result<Self, TagParseError> {
+        let mut lines = vec![];
+        for (i, raw) in text.split_inclusive('\n').enumerate() {
+            let content = raw.trim();
+            if content.is_empty() || content.starts_with('#') {
+                lines.push(Line::Other(raw.to_string()));
+                continue;
+            }
+            let (tag, value) = content
+                .split_once('=')
+                .ok_or_else(|| TagParseError(i + 1, raw.to_string()))?;
+            let eol = &raw[raw.trim_end_matches(['\r', '\n']).len()..];
+            lines.push(Line::Entry {
+                tag: tag.to_string(),
+                value: value.to_string(),
+                raw: Some(raw.to_string()),
+                eol: eol.to_string(),
+            });
+        }
+        Ok(Self {
+            lines,
+            encoding: Encoding::Utf8,
+        })
+    }
+
+    /// Parses a tags file in any encoding [`Encoding::detect`] recognizes, remembering the encoding for
+    /// [`Document::to_bytes`].
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, TagParseError> {
+        let (text, encoding) = decode(bytes);
+        let mut document = Self::parse(&text)?;
+        document.encoding = encoding;
+        Ok(document)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Writes the file in its encoding, byte for byte as it was read when nothing changed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encoding.encode(&self.to_string())
+    }
+
+    /// Returns the value of `tag`, taking the last definition when it is defined more than once.
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        self.entries().filter(|(t, _)| *t == tag).last().map(|(_, value)| value)
+    }
+
+    /// Returns the index of the line with the last definition of `tag`.
+    pub fn line_of(&self, tag: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| matches!(line, Line::Entry { tag: t, .. } if t == tag))
+    }
+
+    /// Returns the tags and values in file order, duplicates included.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry { tag, value, .. } => Some((tag.as_str(), value.as_str())),
+            Line::Other(_) => None,
+        })
+    }
+
+    pub fn to_map(&self) -> HashMap<String, String> {
+        self.entries()
+            .map(|(tag, value)| (tag.to_string(), value.to_string()))
+            .collect()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Changes the value of `tag` where it is last defined, or adds it at the end of the file.
+    pub fn set(&mut self, tag: &str, value: &str) {
+        match self.line_of(tag) {
+            Some(i) => {
+                if let Line::Entry { value: old, raw, .. } = &mut self.lines[i] {
+                    if old != value {
+                        *old = value.to_string();
+                        *raw = None;
+                    }
+                }
+            }
+            None => self.insert(self.lines.len(), tag, value),
+        }
+    }
+
+    /// Inserts a definition of `tag` before the line at `index`, or at the end when `index` is past the last line.
+    pub fn insert(&mut self, index: usize, tag: &str, value: &str) {
+        let eol = self.line_ending().to_string();
+        let index = index.min(self.lines.len());
+        // A last line without a line ending needs one before anything can follow it
+        if index == self.lines.len() {
+            if let Some(last) = self.lines.last_mut() {
+                terminate(last, &eol);
+            }
+        }
+        self.lines.insert(
+            index,
+            Line::Entry {
+                tag: tag.to_string(),
+                value: value.to_string(),
+                raw: None,
+                eol,
+            },
+        );
+    }
+
+    /// Removes every definition of `tag`, returning the value of the last one.
+    pub fn remove(&mut self, tag: &str) -> Option<String> {
+        let value = self.get(tag).map(|value| value.to_string());
+        self.lines
+            .retain(|line| !matches!(line, Line::Entry { tag: t, .. } if t == tag));
+        value
+    }
+
+    // The line ending used by the file, so added lines match it
+    fn line_ending(&self) -> &str {
+        self.lines
+            .iter()
+            .find_map(|line| {
+                let text = match line {
+                    Line::Other(raw) => raw.as_str(),
+                    Line::Entry { eol, .. } => eol.as_str(),
+                };
+                text.ends_with('\n')
+                    .then(|| if text.ends_with("\r\n") { "\r\n" } else { "\n" })
+            })
+            .unwrap_or("\n")
+    }
+}
+
+fn terminate(line: &mut Line, eol: &str) {
+    match line {
+        Line::Other(raw) if !raw.ends_with('\n') => raw.push_str(eol),
+        Line::Entry { raw, eol: line_eol, .. } if line_eol.is_empty() => {
+            if let Some(raw) = raw {
+                raw.push_str(eol);
+            }
+            *line_eol = eol.to_string();
+        }
+        _ => {}
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.lines.iter() {
+            match line {
+                Line::Other(raw) | Line::Entry { raw: Some(raw), .. } => f.write_str(raw)?,
+                Line::Entry {
+                    tag,
+                    value,
+                    raw: None,
+                    eol,
+                } => write!(f, "{tag}={value}{eol}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "# Items\r\ntagA=Sword\r\n\r\ntagB=a=b\r\n  # indented comment\r\ntagC=Shield";
+
+    #[test]
+    fn unchanged_document_is_byte_identical() {
+        let document = Document::parse(TEXT).unwrap();
+        assert_eq!(document.to_string(), TEXT);
+        assert_eq!(document.to_bytes(), TEXT.as_bytes());
+        assert_eq!(document.line_count(), 6);
+        assert_eq!(document.get("tagB"), Some("a=b"));
+        assert_eq!(document.get("tagC"), Some("Shield"));
+    }
+
+    #[test]
+    fn edits_keep_other_lines() {
+        let mut document = Document::parse(TEXT).unwrap();
+        document.set("tagA", "Blade");
+        document.set("tagA", "Blade");
+        assert_eq!(
+            document.to_string(),
+            "# Items\r\ntagA=Blade\r\n\r\ntagB=a=b\r\n  # indented comment\r\ntagC=Shield"
+        );
+
+        // Adding after a last line without a line ending terminates it with the file's line ending
+        document.set("tagD", "Helm");
+        assert_eq!(
+            document.to_string(),
+            "# Items\r\ntagA=Blade\r\n\r\ntagB=a=b\r\n  # indented comment\r\ntagC=Shield\r\ntagD=Helm\r\n"
+        );
+
+        document.insert(1, "tagE", "Ring");
+        assert_eq!(document.remove("tagB"), Some("a=b".to_string()));
+        assert_eq!(document.remove("tagB"), None);
+        assert_eq!(
+            document.to_string(),
+            "# Items\r\ntagE=Ring\r\ntagA=Blade\r\n\r\n  # indented comment\r\ntagC=Shield\r\ntagD=Helm\r\n"
+        );
+        assert_eq!(document.to_bytes(), document.to_string().as_bytes());
+    }
+
+    #[test]
+    fn set_changes_last_definition() {
+        let mut document = Document::parse("tagA=1\ntagA=2\n").unwrap();
+        document.set("tagA", "3");
+        assert_eq!(document.to_string(), "tagA=1\ntagA=3\n");
+        assert_eq!(document.remove("tagA"), Some("3".to_string()));
+        assert_eq!(document.to_string(), "");
+    }
+
+    #[test]
+    fn parse_bytes_keeps_encoding() {
+        for encoding in [
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Utf16LeNoBom,
+        ] {
+            let bytes = encoding.encode("# Text\r\ntagA=Épée\r\n");
+            let mut document = Document::parse_bytes(&bytes).unwrap();
+            assert_eq!(document.encoding(), encoding);
+            assert_eq!(document.get("tagA"), Some("Épée"));
+            assert_eq!(document.to_bytes(), bytes);
+
+            document.set("tagA", "Schwert");
+            assert_eq!(document.to_bytes(), encoding.encode("# Text\r\ntagA=Schwert\r\n"));
+        }
+    }
+}
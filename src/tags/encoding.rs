@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use encoding_rs::{WINDOWS_1250, WINDOWS_1251};
+
+/// The text encoding of a tags file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Utf16LeNoBom,
+    Utf16BeNoBom,
+    /// Cyrillic Windows code page.
+    Windows1251,
+    /// Central European Windows code page.
+    Windows1250,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utf8 => write!(f, "UTF-8"),
+            Self::Utf8Bom => write!(f, "UTF-8 with BOM"),
+            Self::Utf16Le => write!(f, "UTF-16LE"),
+            Self::Utf16Be => write!(f, "UTF-16BE"),
+            Self::Utf16LeNoBom => write!(f, "UTF-16LE without BOM"),
+            Self::Utf16BeNoBom => write!(f, "UTF-16BE without BOM"),
+            Self::Windows1251 => write!(f, "windows-1251"),
+            Self::Windows1250 => write!(f, "windows-1250"),
+        }
+    }
+}
+
+impl Encoding {
+    /// Detects the encoding from a byte order mark, or else from NUL bytes at every other position as UTF-16 has
+    /// for ASCII text, or else from whether the bytes are valid UTF-8, telling the two code pages apart by which one
+    /// decodes to more plausible words.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            Self::Utf8Bom
+        } else if bytes.starts_with(&[0xFF, 0xFE]) {
+            Self::Utf16Le
+        } else if bytes.starts_with(&[0xFE, 0xFF]) {
+            Self::Utf16Be
+        } else if let Some(encoding) = utf16_without_bom(bytes) {
+            encoding
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Self::Utf8
+        } else if cyrillic_score(bytes) > central_european_score(bytes) {
+            Self::Windows1251
+        } else {
+            Self::Windows1250
+        }
+    }
+
+    /// Decodes `bytes` as this encoding, dropping any byte order mark and replacing malformed sequences.
+    pub fn decode(self, bytes: &[u8]) -> Cow<'_, str> {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => {
+                let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+                String::from_utf8_lossy(bytes)
+            }
+            Self::Utf16Le | Self::Utf16Be | Self::Utf16LeNoBom | Self::Utf16BeNoBom => {
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Self::Utf16Le | Self::Utf16LeNoBom => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                let text = char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect::<String>();
+                Cow::Owned(text.strip_prefix('\u{feff}').map(|s| s.to_string()).unwrap_or(text))
+            }
+            Self::Windows1251 => WINDOWS_1251.decode_without_bom_handling(bytes).0,
+            Self::Windows1250 => WINDOWS_1250.decode_without_bom_handling(bytes).0,
+        }
+    }
+
+    /// Encodes `text` in this encoding, with a byte order mark where the encoding has one. Characters the code
+    /// pages cannot represent become numeric character references.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+            Self::Utf16Le => ["\u{feff}", text]
+                .concat()
+                .encode_utf16()
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect(),
+            Self::Utf16Be => ["\u{feff}", text]
+                .concat()
+                .encode_utf16()
+                .flat_map(|unit| unit.to_be_bytes())
+                .collect(),
+            Self::Utf16LeNoBom => text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect(),
+            Self::Utf16BeNoBom => text.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect(),
+            Self::Windows1251 => WINDOWS_1251.encode(text).0.into_owned(),
+            Self::Windows1250 => WINDOWS_1250.encode(text).0.into_owned(),
+        }
+    }
+}
+
+/// Detects the encoding of `bytes` and decodes them.
+pub fn decode(bytes: &[u8]) -> (Cow<'_, str>, Encoding) {
+    let encoding = Encoding::detect(bytes);
+    (encoding.decode(bytes), encoding)
+}
+
+// Tags files are mostly ASCII, which UTF-16 writes with a NUL high byte, while UTF-8 and the code pages never
+// contain NUL bytes at all
+fn utf16_without_bom(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let nuls = |parity: usize| bytes.iter().skip(parity).step_by(2).filter(|&&b| b == 0).count();
+    let (even, odd) = (nuls(0), nuls(1));
+    if odd * 4 >= pairs && even < odd / 4 {
+        Some(Encoding::Utf16LeNoBom)
+    } else if even * 4 >= pairs && odd < even / 4 {
+        Some(Encoding::Utf16BeNoBom)
+    } else {
+        None
+    }
+}
+
+// Counts the non-ASCII letters of the words that read as Cyrillic in windows-1251, where a word is written
+// entirely in Cyrillic rather than mixed with Latin letters
+fn cyrillic_score(bytes: &[u8]) -> usize {
+    score_words(&WINDOWS_1251.decode_without_bom_handling(bytes).0, |word| {
+        word.chars().all(|c| matches!(c, '\u{0400}'..='\u{04FF}'))
+    })
+}
+
+// Counts the non-ASCII letters of the words that read as Central European in windows-1250, where accented letters
+// mix with plain ones, as a longer word made only of accented letters is really Cyrillic read in the wrong code page
+fn central_european_score(bytes: &[u8]) -> usize {
+    score_words(&WINDOWS_1250.decode_without_bom_handling(bytes).0, |word| {
+        word.chars().count() == 1 || word.chars().any(|c| c.is_ascii_alphabetic())
+    })
+}
+
+fn score_words<F: Fn(&str) -> bool>(text: &str, plausible: F) -> usize {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_ascii() && plausible(word))
+        .map(|word| word.chars().filter(|c| !c.is_ascii()).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_byte_order_marks_and_utf8() {
+        assert_eq!(Encoding::detect("tagA=Épée\n".as_bytes()), Encoding::Utf8);
+        assert_eq!(
+            Encoding::detect(&Encoding::Utf8Bom.encode("tagA=Sword\n")),
+            Encoding::Utf8Bom
+        );
+        assert_eq!(
+            Encoding::detect(&Encoding::Utf16Le.encode("tagA=Sword\n")),
+            Encoding::Utf16Le
+        );
+        assert_eq!(
+            Encoding::detect(&Encoding::Utf16Be.encode("tagA=Sword\n")),
+            Encoding::Utf16Be
+        );
+    }
+
+    #[test]
+    fn detect_utf16_without_byte_order_mark() {
+        for (text, encoding) in [
+            ("tagA=Sword\r\n", Encoding::Utf16LeNoBom),
+            ("tagA=Sword\r\n", Encoding::Utf16BeNoBom),
+            ("tagA=剑\r\ntagB=盾牌\r\n", Encoding::Utf16LeNoBom),
+            ("tagA=Меч\r\n", Encoding::Utf16BeNoBom),
+        ] {
+            let bytes = encoding.encode(text);
+            assert_eq!(decode(&bytes), (Cow::Borrowed(text), encoding), "{text}");
+        }
+        let tags = crate::tags::parse(&Encoding::Utf16LeNoBom.encode("tagA=Sword\r\n")).unwrap();
+        assert_eq!(tags["tagA"], "Sword");
+    }
+
+    #[test]
+    fn detect_code_pages_of_short_files() {
+        let files = [
+            ("tagA=Żółw\ntagB=Łuk\ntagC=Miecz\n", Encoding::Windows1250),
+            ("t=Štít\nu=Kůň\n", Encoding::Windows1250),
+            ("tagA=Меч\ntagB=Щит\n", Encoding::Windows1251),
+            ("tagA=Щит\n", Encoding::Windows1251),
+            ("tagA={^W}Меч Ярости{^w}\n", Encoding::Windows1251),
+        ];
+        for (text, encoding) in files {
+            let bytes = encoding.encode(text);
+            assert_eq!(decode(&bytes), (Cow::Borrowed(text), encoding), "{text}");
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::fmt::{self, Write};
+
+/// The markup of a tag value, parsed into text runs, color changes, line breaks, placeholders and references.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TagText {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    /// A `{^X}` color code, holding the color letter as written.
+    Color(char),
+    /// `^n` or `{^n}`, in either case, holding the markup as written.
+    LineBreak(String),
+    Placeholder(Placeholder),
+    /// A `$tagName` reference to another tag.
+    Reference(String),
+}
+
+/// A printf-style `{%+.0f0}` placeholder, filled in by the game with the argument at `index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    /// Flags, width and precision, such as `+.0`.
+    pub spec: String,
+    /// Conversion letter, such as `f`, `d`, `s` or `t`.
+    pub conversion: char,
+    pub index: u32,
+}
+
+impl TagText {
+    /// Parses the markup in `text`. Anything that is not well-formed markup is kept as text.
+    pub fn parse(text: &str) -> Self {
+        let mut segments = vec![];
+        let mut run = String::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let markup = match c {
+                '{' => parse_braced(rest),
+                '^' if rest[1..].starts_with(['n', 'N']) => Some((Segment::LineBreak(rest[..2].to_string()), 2)),
+                '$' => parse_reference(rest),
+                _ => None,
+            };
+            match markup {
+                Some((segment, len)) => {
+                    if !run.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut run)));
+                    }
+                    segments.push(segment);
+                    rest = &rest[len..];
+                }
+                None => {
+                    run.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        if !run.is_empty() {
+            segments.push(Segment::Text(run));
+        }
+        Self { segments }
+    }
+
+    /// Renders the text without colors, with line breaks as `\n` and placeholders and references as written.
+    pub fn to_plain(&self) -> String {
+        let mut out = String::new();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Color(_) => {}
+                Segment::LineBreak(_) => out.push('\n'),
+                _ => write_source(&mut out, segment),
+            }
+        }
+        out
+    }
+
+    /// Renders the text for a terminal, with colors as ANSI escape codes.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        let mut colored = false;
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Color(color) => {
+                    let code = ansi_code(color.to_ascii_lowercase());
+                    let _ = write!(out, "\x1b[{code}m");
+                    colored = code != 0;
+                }
+                Segment::LineBreak(_) => out.push('\n'),
+                _ => write_source(&mut out, segment),
+            }
+        }
+        if colored {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+
+    /// Renders the text as HTML, wrapping colored runs in `<span class="tag-color-x">`, placeholders in
+    /// `<span class="tag-placeholder">` and references in `<span class="tag-reference">`.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let mut in_color = false;
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(text) => escape_html(&mut out, text),
+                Segment::Color(color) => {
+                    if in_color {
+                        out.push_str("</span>");
+                    }
+                    let _ = write!(out, "<span class=\"tag-color-{}\">", color.to_ascii_lowercase());
+                    in_color = true;
+                }
+                Segment::LineBreak(_) => out.push_str("<br>"),
+                Segment::Placeholder(_) => {
+                    out.push_str("<span class=\"tag-placeholder\">");
+                    let mut source = String::new();
+                    write_source(&mut source, segment);
+                    escape_html(&mut out, &source);
+                    out.push_str("</span>");
+                }
+                Segment::Reference(name) => {
+                    out.push_str("<span class=\"tag-reference\">$");
+                    escape_html(&mut out, name);
+                    out.push_str("</span>");
+                }
+            }
+        }
+        if in_color {
+            out.push_str("</span>");
+        }
+        out
+    }
+}
+
+impl fmt::Display for TagText {
+    /// Writes the markup back in the tags file syntax.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        for segment in self.segments.iter() {
+            write_source(&mut out, segment);
+        }
+        f.write_str(&out)
+    }
+}
+
+fn parse_braced(s: &str) -> Option<(Segment, usize)> {
+    let end = s.find('}')?;
+    let inner = &s[1..end];
+    let segment = if let Some(code) = inner.strip_prefix('^') {
+        let mut chars = code.chars();
+        match (chars.next(), chars.next()) {
+            (Some('n' | 'N'), None) => Segment::LineBreak(s[..end + 1].to_string()),
+            (Some(c), None) if c.is_ascii_alphabetic() => Segment::Color(c),
+            _ => return None,
+        }
+    } else if let Some(spec) = inner.strip_prefix('%') {
+        Segment::Placeholder(parse_placeholder(spec)?)
+    } else {
+        return None;
+    };
+    Some((segment, end + 1))
+}
+
+fn parse_placeholder(spec: &str) -> Option<Placeholder> {
+    let digits = spec.len() - spec.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (head, index) = spec.split_at(spec.len() - digits);
+    let conversion = head.chars().last().filter(|c| c.is_ascii_alphabetic())?;
+    let flags = &head[..head.len() - 1];
+    if !flags.chars().all(|c| c.is_ascii_digit() || "+-. #".contains(c)) {
+        return None;
+    }
+    Some(Placeholder {
+        spec: flags.to_string(),
+        conversion,
+        index: index.parse().ok()?,
+    })
+}
+
+fn parse_reference(s: &str) -> Option<(Segment, usize)> {
+    let name_len = s[1..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(s.len() - 1);
+    if !s[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+    Some((Segment::Reference(s[1..1 + name_len].to_string()), 1 + name_len))
+}
+
+fn write_source(out: &mut String, segment: &Segment) {
+    let _ = match segment {
+        Segment::Text(text) => write!(out, "{text}"),
+        Segment::Color(color) => write!(out, "{{^{color}}}"),
+        Segment::LineBreak(markup) => write!(out, "{markup}"),
+        Segment::Placeholder(placeholder) => write!(
+            out,
+            "{{%{}{}{}}}",
+            placeholder.spec, placeholder.conversion, placeholder.index
+        ),
+        Segment::Reference(name) => write!(out, "${name}"),
+    };
+}
+
+// Closest standard terminal color for each of the game's color letters, 0 resetting to the default
+fn ansi_code(color: char) -> u8 {
+    match color {
+        'r' | 'm' => 31,
+        'g' | 'l' => 32,
+        'y' | 'o' | 'k' => 33,
+        'b' | 'i' => 34,
+        'p' | 'f' => 35,
+        'a' | 'c' | 't' => 36,
+        's' | 'd' => 90,
+        'w' => 97,
+        _ => 0,
+    }
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholder(spec: &str, conversion: char, index: u32) -> Segment {
+        Segment::Placeholder(Placeholder {
+            spec: spec.to_string(),
+            conversion,
+            index,
+        })
+    }
+
+    #[test]
+    fn parse_markup() {
+        let text = TagText::parse("{^Y}+{%+.0f0}% Damage^n{%t0} for $tagName_2, costs $5{^n}");
+        assert_eq!(
+            text.segments,
+            [
+                Segment::Color('Y'),
+                Segment::Text("+".to_string()),
+                placeholder("+.0", 'f', 0),
+                Segment::Text("% Damage".to_string()),
+                Segment::LineBreak("^n".to_string()),
+                placeholder("", 't', 0),
+                Segment::Text(" for ".to_string()),
+                Segment::Reference("tagName_2".to_string()),
+                Segment::Text(", costs $5".to_string()),
+                Segment::LineBreak("{^n}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_malformed_markup_as_text() {
+        for text in [
+            "{^}", "{^ab}", "{%}", "{%0}", "{%f}", "{%f0", "{x}", "{", "}", "^", "$", "$ x",
+        ] {
+            assert_eq!(
+                TagText::parse(text).segments,
+                [Segment::Text(text.to_string())],
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn display_writes_markup_back() {
+        for text in [
+            "{%t0}{^w} costs $5 {^N}",
+            "^N{^n}^n{^Y}{%+.0f0}{%-5d12}$ref end",
+            "{^} {%f} plain",
+            "",
+        ] {
+            assert_eq!(TagText::parse(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn render_plain_and_ansi() {
+        let text = TagText::parse("{^R}Fire{^W}^nDeals {%d0} $dmg");
+        assert_eq!(text.to_plain(), "Fire\nDeals {%d0} $dmg");
+        assert_eq!(text.to_ansi(), "\x1b[31mFire\x1b[97m\nDeals {%d0} $dmg\x1b[0m");
+        assert_eq!(TagText::parse("Plain").to_ansi(), "Plain");
+    }
+
+    #[test]
+    fn render_html() {
+        let text = TagText::parse("<b>&\"'{^G}Heal {%+.0f0}{^n}$tagName");
+        assert_eq!(
+            text.to_html(),
+            "&lt;b&gt;&amp;&quot;&#39;<span class=\"tag-color-g\">Heal \
+             <span class=\"tag-placeholder\">{%+.0f0}</span><br>\
+             <span class=\"tag-reference\">$tagName</span></span>"
+        );
+    }
+}
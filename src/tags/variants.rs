@@ -0,0 +1,233 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Gender {
+    #[default]
+    Masculine,
+    Feminine,
+    Neuter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Number {
+    #[default]
+    Singular,
+    Plural,
+}
+
+/// A gender and number, written as markers such as `[ms]` (masculine singular) or `[fp]` (feminine plural).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Form {
+    pub gender: Gender,
+    pub number: Number,
+}
+
+impl Form {
+    pub fn new(gender: Gender, number: Number) -> Self {
+        Self { gender, number }
+    }
+
+    /// Returns the form marked at the start of `text`, which is how item names state the gender adjectives must
+    /// agree with, such as `[fs]Klinge`.
+    pub fn of(text: &str) -> Option<Self> {
+        parse_marker(text).map(|(form, _)| form)
+    }
+
+    fn from_marker(marker: &str) -> Option<Self> {
+        let mut chars = marker.chars().map(|c| c.to_ascii_lowercase());
+        let gender = match chars.next()? {
+            'm' => Gender::Masculine,
+            'f' => Gender::Feminine,
+            'n' => Gender::Neuter,
+            _ => return None,
+        };
+        let number = match chars.next()? {
+            's' => Number::Singular,
+            'p' => Number::Plural,
+            _ => return None,
+        };
+        chars.next().is_none().then_some(Self { gender, number })
+    }
+}
+
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gender = match self.gender {
+            Gender::Masculine => 'm',
+            Gender::Feminine => 'f',
+            Gender::Neuter => 'n',
+        };
+        let number = match self.number {
+            Number::Singular => 's',
+            Number::Plural => 'p',
+        };
+        write!(f, "[{gender}{number}]")
+    }
+}
+
+/// The variants of a tag value such as `[ms]Rostiger[fs]Rostige[ns]Rostiges`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Variants {
+    /// Text before the first marker, which is the whole value when it has no markers.
+    pub plain: String,
+    pub forms: Vec<(Form, String)>,
+}
+
+impl Variants {
+    pub fn parse(text: &str) -> Self {
+        let mut variants = Self::default();
+        let mut rest = text;
+        let mut current: Option<(Form, String)> = None;
+        while !rest.is_empty() {
+            if let Some((form, len)) = parse_marker(rest) {
+                variants.forms.extend(current.take());
+                current = Some((form, String::new()));
+                rest = &rest[len..];
+                continue;
+            }
+            let c = rest.chars().next().unwrap_or_default();
+            match current.as_mut() {
+                Some((_, text)) => text.push(c),
+                None => variants.plain.push(c),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        variants.forms.extend(current);
+        variants
+    }
+
+    /// Returns the variant for `form`, falling back to the singular of the same gender, then to the masculine
+    /// singular, the first variant and finally the text without markers.
+    pub fn get(&self, form: Form) -> &str {
+        let find = |wanted: Form| self.forms.iter().find(|(form, _)| *form == wanted);
+        find(form)
+            .or_else(|| find(Form::new(form.gender, Number::Singular)))
+            .or_else(|| find(Form::default()))
+            .or_else(|| self.forms.first())
+            .map(|(_, text)| text.as_str())
+            .unwrap_or(&self.plain)
+    }
+}
+
+/// Picks the variant of `text` for `form`, returning `text` unchanged when it has no markers.
+pub fn select_variant(text: &str, form: Form) -> String {
+    let variants = Variants::parse(text);
+    if variants.forms.is_empty() {
+        return text.to_string();
+    }
+    variants.get(form).to_string()
+}
+
+fn parse_marker(s: &str) -> Option<(Form, usize)> {
+    let inner = s.strip_prefix('[')?.get(..3)?;
+    let marker = inner.strip_suffix(']')?;
+    Form::from_marker(marker).map(|form| (form, 4))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::affix::Affix;
+    use crate::arz::{DatabaseValue, Record};
+
+    const RUSTY: &str = "[ms]Rostiger[fs]Rostige[ns]Rostiges";
+
+    fn form(gender: Gender, number: Number) -> Form {
+        Form::new(gender, number)
+    }
+
+    #[test]
+    fn parse_markers() {
+        let variants = Variants::parse(RUSTY);
+        assert_eq!(variants.plain, "");
+        assert_eq!(
+            variants.forms,
+            [
+                (form(Gender::Masculine, Number::Singular), "Rostiger".to_string()),
+                (form(Gender::Feminine, Number::Singular), "Rostige".to_string()),
+                (form(Gender::Neuter, Number::Singular), "Rostiges".to_string()),
+            ]
+        );
+
+        let variants = Variants::parse("Alt [FP]Rostige[mp]Rostige [x]");
+        assert_eq!(variants.plain, "Alt ");
+        assert_eq!(
+            variants.forms,
+            [
+                (form(Gender::Feminine, Number::Plural), "Rostige".to_string()),
+                (form(Gender::Masculine, Number::Plural), "Rostige [x]".to_string()),
+            ]
+        );
+        assert_eq!(Variants::parse("No [markers] here").plain, "No [markers] here");
+    }
+
+    #[test]
+    fn get_falls_back_in_order() {
+        let variants = Variants::parse("[ms]A[fs]B[fp]C");
+        assert_eq!(variants.get(form(Gender::Feminine, Number::Plural)), "C");
+        // The singular of the same gender
+        assert_eq!(variants.get(form(Gender::Masculine, Number::Plural)), "A");
+        // The masculine singular
+        assert_eq!(variants.get(form(Gender::Neuter, Number::Plural)), "A");
+
+        // The first variant
+        let variants = Variants::parse("[fs]B[np]C");
+        assert_eq!(variants.get(form(Gender::Masculine, Number::Singular)), "B");
+        assert_eq!(variants.get(form(Gender::Neuter, Number::Plural)), "C");
+
+        // The text without markers
+        assert_eq!(Variants::parse("Rusty").get(Form::default()), "Rusty");
+    }
+
+    #[test]
+    fn form_of_item_names() {
+        assert_eq!(Form::of("[fs]Klinge"), Some(form(Gender::Feminine, Number::Singular)));
+        assert_eq!(Form::of("[np]Stiefel"), Some(form(Gender::Neuter, Number::Plural)));
+        assert_eq!(Form::of("Klinge[fs]"), None);
+        assert_eq!(Form::of("[xs]Klinge"), None);
+        assert_eq!(Form::of(""), None);
+        assert_eq!(form(Gender::Feminine, Number::Plural).to_string(), "[fp]");
+    }
+
+    #[test]
+    fn select_variant_of_text() {
+        assert_eq!(
+            select_variant(RUSTY, form(Gender::Feminine, Number::Singular)),
+            "Rostige"
+        );
+        assert_eq!(select_variant(RUSTY, form(Gender::Neuter, Number::Plural)), "Rostiges");
+        assert_eq!(
+            select_variant(RUSTY, form(Gender::Masculine, Number::Plural)),
+            "Rostiger"
+        );
+        assert_eq!(
+            select_variant("{^y}Rusty [sic]", form(Gender::Feminine, Number::Plural)),
+            "{^y}Rusty [sic]"
+        );
+    }
+
+    #[test]
+    fn localize_affixes_by_form() {
+        let record = Record {
+            id: "records/items/lootaffixes/prefix/rusty.dbr".to_string(),
+            kind: "LootRandomizer".to_string(),
+            data: HashMap::from([(
+                "lootRandomizerName".to_string(),
+                DatabaseValue::String("tagRusty".to_string()),
+            )]),
+        };
+        let affix = Affix::try_from(record).unwrap();
+        let tags = HashMap::from([("tagRusty".to_string(), RUSTY.to_string())]);
+
+        assert_eq!(affix.localize(&tags), "Rostiger");
+        let blade = Form::of("[fs]Klinge").unwrap();
+        assert_eq!(affix.localize_as(&tags, blade), "Rostige");
+        assert_eq!(
+            affix.localize_as(&tags, form(Gender::Neuter, Number::Plural)),
+            "Rostiges"
+        );
+        assert_eq!(affix.localize(&HashMap::new()), "tagRusty");
+    }
+}